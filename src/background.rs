@@ -0,0 +1,35 @@
+use crate::ray::Ray;
+use crate::vec3::Color;
+
+/// Radiance returned for rays that escape the scene.
+#[derive(Clone, Copy)]
+pub enum Background {
+    Solid(Color),
+    // Blend from `bottom` to `top` along the ray's vertical direction
+    Gradient { bottom: Color, top: Color },
+    Black,
+}
+
+impl Background {
+    pub fn color(&self, ray: Ray) -> Color {
+        match *self {
+            Background::Solid(color) => color,
+            Background::Gradient { bottom, top } => {
+                let unit_direction = ray.direction.normalize();
+                let t = 0.5 * (unit_direction.y + 1.0);
+                (1.0 - t) * bottom + t * top
+            }
+            Background::Black => Color::zeros(),
+        }
+    }
+}
+
+impl Default for Background {
+    // Blue to white sky
+    fn default() -> Self {
+        Background::Gradient {
+            bottom: Color::new(1.0, 1.0, 1.0),
+            top: Color::new(0.5, 0.7, 1.0),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::ray::Ray;
+use crate::world::World;
+use crate::Num;
+use rand::RngCore;
+use std::cmp::Ordering;
+use std::ops::Range;
+
+/// Bounding volume hierarchy over the objects of a [`World`].
+///
+/// Objects that cannot report a bounding box are kept aside and tested linearly, so the hierarchy
+/// always returns the same closest hit as [`World::hit`].
+pub struct Bvh {
+    root: Option<Node>,
+    unbounded: World,
+}
+
+enum Node {
+    Leaf {
+        object: Box<dyn Hittable>,
+        bbox: Aabb,
+    },
+    Branch {
+        left: Box<Node>,
+        right: Box<Node>,
+        bbox: Aabb,
+    },
+}
+
+impl Bvh {
+    pub fn new(world: World, time: Range<Num>) -> Self {
+        let mut bounded = vec![];
+        let mut unbounded = World(vec![]);
+        for object in world.0 {
+            match object.bounding_box(time.clone()) {
+                Some(bbox) => bounded.push((object, bbox)),
+                None => unbounded.add(object),
+            }
+        }
+
+        Self {
+            root: Node::build(bounded),
+            unbounded,
+        }
+    }
+}
+
+impl Node {
+    fn build(mut objects: Vec<(Box<dyn Hittable>, Aabb)>) -> Option<Self> {
+        if objects.len() <= 1 {
+            return objects
+                .pop()
+                .map(|(object, bbox)| Node::Leaf { object, bbox });
+        }
+
+        // Split at the median centroid along the axis where the centroids are most spread out
+        let centroids = objects
+            .iter()
+            .map(|(_, bbox)| Aabb::new(bbox.centroid(), bbox.centroid()))
+            .reduce(Aabb::surrounding)
+            .unwrap();
+        let axis = centroids.longest_axis();
+        objects.sort_by(|(_, a), (_, b)| {
+            a.centroid()[axis]
+                .partial_cmp(&b.centroid()[axis])
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let rest = objects.split_off(objects.len() / 2);
+        let left = Node::build(objects).unwrap();
+        let right = Node::build(rest).unwrap();
+        let bbox = left.bbox().surrounding(right.bbox());
+
+        Some(Node::Branch {
+            left: Box::new(left),
+            right: Box::new(right),
+            bbox,
+        })
+    }
+
+    fn bbox(&self) -> Aabb {
+        match self {
+            Node::Leaf { bbox, .. } | Node::Branch { bbox, .. } => *bbox,
+        }
+    }
+
+    fn hit(&self, ray: Ray, range: Range<Num>, rng: &mut dyn RngCore) -> Option<HitRecord<'_>> {
+        if !self.bbox().hit(ray, range.clone()) {
+            return None;
+        }
+        match self {
+            Node::Leaf { object, .. } => object.hit(ray, range, rng),
+            Node::Branch { left, right, .. } => {
+                let hit_left = left.hit(ray, range.clone(), rng);
+                let closest = hit_left.map_or(range.end, |rec| rec.t);
+                right.hit(ray, range.start..closest, rng).or(hit_left)
+            }
+        }
+    }
+}
+
+impl Hittable for Bvh {
+    fn hit(&self, ray: Ray, range: Range<Num>, rng: &mut dyn RngCore) -> Option<HitRecord<'_>> {
+        let hit_tree = self
+            .root
+            .as_ref()
+            .and_then(|n| n.hit(ray, range.clone(), rng));
+        let closest = hit_tree.map_or(range.end, |rec| rec.t);
+        self.unbounded
+            .hit(ray, range.start..closest, rng)
+            .or(hit_tree)
+    }
+
+    fn bounding_box(&self, _time: Range<Num>) -> Option<Aabb> {
+        if !self.unbounded.0.is_empty() {
+            return None;
+        }
+        self.root.as_ref().map(|n| n.bbox())
+    }
+}
@@ -1,9 +1,9 @@
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::{Point3, Vector3};
 use crate::Num;
-use rand::{random, thread_rng, Rng};
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
 use std::ops::Range;
-use std::time::Duration;
 
 pub struct Camera {
     pub origin: Point3,
@@ -14,29 +14,31 @@ pub struct Camera {
     pub v: Vector3,
     lower_left_corner: Vector3,
     lens_radius: Num,
-    focus_distance: Num,
     exposure: Range<Num>,
 }
 
 impl Camera {
     pub const ASPECT_RATIO: Num = 3. / 2.;
     pub const APERTURE: Num = 0.1;
-    pub(crate) fn new(
+    pub const FOCUS_DISTANCE: Num = 10.0;
+    pub fn new(
         look_from: Point3,
         look_at: Point3,
         vup: Vector3,
         vfov: Num,
+        aspect_ratio: Num,
+        lens: Lens,
         exposure: Range<Num>,
     ) -> Self {
         let theta = vfov.to_radians();
         let h = (theta / 2.).tan();
         let viewport = {
             let height = 2. * h;
-            let width = Camera::ASPECT_RATIO * height;
+            let width = aspect_ratio * height;
             Viewport { height, width }
         };
 
-        let focus_distance = 10.0;
+        let focus_distance = lens.focus_distance;
 
         let w = (look_from - look_at).normalize();
         let u = vup.cross(w).normalize();
@@ -55,28 +57,66 @@ impl Camera {
             u,
             v,
             lower_left_corner,
-            lens_radius: Self::APERTURE / 2.,
-            focus_distance,
+            lens_radius: lens.aperture / 2.,
             exposure,
         }
     }
 
-    pub(crate) fn llc(&self) -> Vector3 {
+    pub fn exposure(&self) -> Range<Num> {
+        self.exposure.clone()
+    }
+
+    pub fn llc(&self) -> Vector3 {
         self.lower_left_corner
     }
 
-    pub(crate) fn cast_ray(&self, u: Num, v: Num) -> Ray {
-        let rd = self.lens_radius * Vector3::random_in_unit_sphere(&mut rand::thread_rng());
-        let offset = self.u * rd.x + self.v * rd.y;
+    /// Ray towards `(u, v)` on the viewport, counted from its lower left corner, leaving from a
+    /// point on the lens at a time within the exposure, both taken from `sampler`.
+    pub fn cast_ray(&self, u: Num, v: Num, sampler: &mut dyn Sampler) -> Ray {
+        let (x, y) = concentric_disk(sampler.get_2d());
+        let offset = self.lens_radius * (self.u * x + self.v * y);
+        let time =
+            self.exposure.start + sampler.get_1d() * (self.exposure.end - self.exposure.start);
         Ray::from(
             self.origin + offset,
             self.lower_left_corner + u * self.horizontal + v * self.vertical - self.origin - offset,
-            rand::thread_rng().gen_range(self.exposure.clone()),
+            time,
         )
     }
 }
 
+// Shirley and Chiu's mapping of the unit square onto the unit disk, taking squares around the
+// center to circles so that evenly spread points stay evenly spread
+fn concentric_disk([a, b]: [Num; 2]) -> (Num, Num) {
+    let (x, y) = (2. * a - 1., 2. * b - 1.);
+    if x == 0. && y == 0. {
+        return (0., 0.);
+    }
+    let (r, theta) = if x.abs() > y.abs() {
+        (x, FRAC_PI_4 * (y / x))
+    } else {
+        (y, FRAC_PI_2 - FRAC_PI_4 * (x / y))
+    };
+    (r * theta.cos(), r * theta.sin())
+}
+
 pub struct Viewport {
     pub width: Num,
     pub height: Num,
 }
+
+/// Thin lens of the camera, objects at `focus_distance` are in perfect focus.
+#[derive(Clone, Copy)]
+pub struct Lens {
+    pub aperture: Num,
+    pub focus_distance: Num,
+}
+
+impl Default for Lens {
+    fn default() -> Self {
+        Self {
+            aperture: Camera::APERTURE,
+            focus_distance: Camera::FOCUS_DISTANCE,
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use crate::film::Film;
+use crate::vec3::Color;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"RTIOWCKP";
+const VERSION: u32 = 2;
+
+/// Saved state of an unfinished render: the film with its per-pixel sample counts, the seed of
+/// the random streams, the samples per pixel the render aims for and a fingerprint of whatever
+/// else determines the samples.
+///
+/// Every sample draws from a stream picked by the seed, the pixel and the sample index, so this
+/// is all it takes to continue exactly where the render stopped.
+pub struct Checkpoint {
+    pub film: Film,
+    pub seed: u64,
+    /// Samples per pixel the render was started for, which stratified samples depend on
+    pub samples: usize,
+    pub fingerprint: u64,
+}
+
+impl Checkpoint {
+    /// Writes the checkpoint to `path`, through a temporary file so that a crash midway leaves
+    /// the previous checkpoint intact.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.film.width() as u64).to_le_bytes())?;
+        writer.write_all(&(self.film.height() as u64).to_le_bytes())?;
+        writer.write_all(&self.seed.to_le_bytes())?;
+        writer.write_all(&(self.samples as u64).to_le_bytes())?;
+        writer.write_all(&self.fingerprint.to_le_bytes())?;
+        for y in 0..self.film.height() {
+            for x in 0..self.film.width() {
+                let c = self.film.accumulated(x, y);
+                for v in [c.x, c.y, c.z] {
+                    writer.write_all(&v.to_le_bytes())?;
+                }
+                writer.write_all(&self.film.sample_count(x, y).to_le_bytes())?;
+            }
+        }
+        writer.flush()?;
+        drop(writer);
+
+        fs::rename(&tmp, path)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let invalid = |msg: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), msg),
+            )
+        };
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a checkpoint file"));
+        }
+        if read_u32(&mut reader)? != VERSION {
+            return Err(invalid("unsupported checkpoint version"));
+        }
+        let width = read_u64(&mut reader)? as usize;
+        let height = read_u64(&mut reader)? as usize;
+        let seed = read_u64(&mut reader)?;
+        let samples = read_u64(&mut reader)? as usize;
+        let fingerprint = read_u64(&mut reader)?;
+        if width.checked_mul(height).is_none_or(|n| n > 1 << 30) {
+            return Err(invalid("unreasonable image size"));
+        }
+
+        let mut film = Film::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let r = f32::from_bits(read_u32(&mut reader)?);
+                let g = f32::from_bits(read_u32(&mut reader)?);
+                let b = f32::from_bits(read_u32(&mut reader)?);
+                let samples = read_u32(&mut reader)?;
+                film.set_accumulated(x, y, Color::new(r, g, b), samples);
+            }
+        }
+        if reader.read(&mut [0])? != 0 {
+            return Err(invalid("trailing data after the pixels"));
+        }
+
+        Ok(Self {
+            film,
+            seed,
+            samples,
+            fingerprint,
+        })
+    }
+}
+
+/// 64 bit FNV-1a hash of `bytes`, stable across platforms and compiler versions unlike the
+/// standard library hashers, for fingerprinting the scene and settings of a checkpoint.
+pub fn fingerprint(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        render_into, scenes, Bvh, Image, IntegratorKind, RenderSettings, SamplerKind, SceneView,
+    };
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn film_bits(film: &Film) -> Vec<[u32; 4]> {
+        (0..film.height())
+            .flat_map(|y| (0..film.width()).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let c = film.accumulated(x, y);
+                [
+                    c.x.to_bits(),
+                    c.y.to_bits(),
+                    c.z.to_bits(),
+                    film.sample_count(x, y),
+                ]
+            })
+            .collect()
+    }
+
+    // Saves and reloads `checkpoint` through a file of its own
+    fn round_trip(checkpoint: &Checkpoint, name: &str) -> Checkpoint {
+        let path = std::env::temp_dir().join(format!("rtiow-{}-{}.ckpt", std::process::id(), name));
+        checkpoint.save(&path).unwrap();
+        let loaded = Checkpoint::load(&path);
+        fs::remove_file(&path).unwrap();
+        loaded.unwrap()
+    }
+
+    #[test]
+    fn round_trip_keeps_everything() {
+        let mut film = Film::new(3, 2);
+        film.set_accumulated(0, 0, Color::new(1.5, -0., f32::MAX), 7);
+        film.set_accumulated(2, 1, Color::new(f32::MIN_POSITIVE, 2., 3.), u32::MAX);
+        let checkpoint = Checkpoint {
+            film,
+            seed: u64::MAX,
+            samples: 100,
+            fingerprint: 0x0123_4567_89ab_cdef,
+        };
+
+        let loaded = round_trip(&checkpoint, "round-trip");
+        assert!(film_bits(&loaded.film) == film_bits(&checkpoint.film));
+        assert_eq!(loaded.seed, checkpoint.seed);
+        assert_eq!(loaded.samples, checkpoint.samples);
+        assert_eq!(loaded.fingerprint, checkpoint.fingerprint);
+    }
+
+    #[test]
+    fn resume_matches_an_uninterrupted_render() {
+        let scene = scenes::find("cornell_box")
+            .unwrap()
+            .build(&mut StdRng::seed_from_u64(0));
+        let world = Bvh::new(scene.world, scene.camera.exposure());
+        let view = SceneView {
+            world: &world,
+            lights: &scene.lights,
+            background: &scene.background,
+        };
+        let integrator = IntegratorKind::Nee.build(10, 3);
+        let image = Image::from_width(scene.image.aspect_ratio, 20);
+        let settings = RenderSettings {
+            samples: 6,
+            sampler: SamplerKind::Stratified,
+            tile_size: 8,
+            ..RenderSettings::default()
+        };
+        let render = |film: &mut Film, settings: &RenderSettings, cancel: &AtomicBool| {
+            render_into(
+                &view,
+                integrator.as_ref(),
+                &scene.camera,
+                settings,
+                film,
+                cancel,
+                |_| {},
+            )
+        };
+
+        let mut uninterrupted = Film::new(image.width, image.height);
+        render(&mut uninterrupted, &settings, &AtomicBool::new(false));
+
+        // A first pass of 4 samples, stopped after its first tile
+        let mut film = Film::new(image.width, image.height);
+        let first_pass = RenderSettings {
+            samples: 4,
+            total_samples: Some(settings.samples),
+            ..settings
+        };
+        let stop = AtomicBool::new(false);
+        render_into(
+            &view,
+            integrator.as_ref(),
+            &scene.camera,
+            &first_pass,
+            &mut film,
+            &stop,
+            |_| stop.store(true, Ordering::Relaxed),
+        );
+        assert_eq!(film.min_sample_count(), 0);
+
+        let checkpoint = Checkpoint {
+            film,
+            seed: settings.seed,
+            samples: settings.samples,
+            fingerprint: 0,
+        };
+        let mut resumed = round_trip(&checkpoint, "resume").film;
+        render(&mut resumed, &first_pass, &AtomicBool::new(false));
+        render(&mut resumed, &settings, &AtomicBool::new(false));
+        assert!(film_bits(&resumed) == film_bits(&uninterrupted));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::output::{self, ImageFormat};
+use crate::tonemap::{self, DisplayTransform};
+use crate::vec3::Color;
+use crate::Num;
+use std::io;
+use std::path::Path;
+
+/// Framebuffer accumulating radiance samples, row-major from the top row.
+#[derive(Clone, Debug)]
+pub struct Film {
+    width: usize,
+    height: usize,
+    radiance: Vec<Color>,
+    samples: Vec<u32>,
+}
+
+/// Summary of the radiance stored in a [`Film`].
+#[derive(Clone, Copy, Debug)]
+pub struct FilmStats {
+    /// Mean of the average pixel radiance over the pixels with a finite value
+    pub mean: Color,
+    pub min_luminance: Num,
+    pub max_luminance: Num,
+    /// Pixels whose radiance is NaN or infinite
+    pub invalid: usize,
+    /// Total number of samples taken
+    pub samples: u64,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            radiance: vec![Color::zeros(); width * height],
+            samples: vec![0; width * height],
+        }
+    }
+
+    /// Film holding `radiance` summed over `samples` samples in every pixel.
+    pub fn from_radiance(width: usize, height: usize, radiance: Vec<Color>, samples: u32) -> Self {
+        assert_eq!(radiance.len(), width * height);
+        Self {
+            width,
+            height,
+            radiance,
+            samples: vec![samples; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Adds one radiance sample to the pixel in column `x` and row `y`, counted from the top.
+    pub fn add_sample(&mut self, x: usize, y: usize, radiance: Color) {
+        let i = self.index(x, y);
+        self.radiance[i] += radiance;
+        self.samples[i] += 1;
+    }
+
+    /// Adds `count` samples summing to `radiance` to the pixel in column `x` and row `y`.
+    pub fn add_samples(&mut self, x: usize, y: usize, radiance: Color, count: u32) {
+        let i = self.index(x, y);
+        self.radiance[i] += radiance;
+        self.samples[i] += count;
+    }
+
+    /// Replaces the summed radiance and sample count of a pixel.
+    pub fn set_accumulated(&mut self, x: usize, y: usize, radiance: Color, samples: u32) {
+        let i = self.index(x, y);
+        self.radiance[i] = radiance;
+        self.samples[i] = samples;
+    }
+
+    /// Average radiance of a pixel, black while it has no samples.
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        let i = self.index(x, y);
+        average(self.radiance[i], self.samples[i])
+    }
+
+    /// Radiance summed over all samples of a pixel.
+    pub fn accumulated(&self, x: usize, y: usize) -> Color {
+        self.radiance[self.index(x, y)]
+    }
+
+    pub fn sample_count(&self, x: usize, y: usize) -> u32 {
+        self.samples[self.index(x, y)]
+    }
+
+    /// Fewest samples any pixel has.
+    pub fn min_sample_count(&self) -> u32 {
+        self.samples.iter().copied().min().unwrap_or(0)
+    }
+
+    /// Average radiance of every pixel, row-major from the top row.
+    pub fn pixels(&self) -> Vec<Color> {
+        self.radiance
+            .iter()
+            .zip(&self.samples)
+            .map(|(&c, &n)| average(c, n))
+            .collect()
+    }
+
+    /// Display-encoded 8 bit RGB of every pixel.
+    pub fn to_rgb8(&self, transform: &DisplayTransform) -> Vec<u8> {
+        output::to_rgb8(&self.pixels(), transform)
+    }
+
+    /// Writes the film to `path`, in `format` or else the one matching the extension.
+    pub fn save(
+        &self,
+        path: &Path,
+        format: Option<ImageFormat>,
+        transform: &DisplayTransform,
+    ) -> io::Result<()> {
+        output::save(path, format, self, transform)
+    }
+
+    pub fn stats(&self) -> FilmStats {
+        let mut stats = FilmStats {
+            mean: Color::zeros(),
+            min_luminance: Num::INFINITY,
+            max_luminance: Num::NEG_INFINITY,
+            invalid: 0,
+            samples: self.samples.iter().map(|&n| n as u64).sum(),
+        };
+        let mut sum = [0f64; 3];
+        for c in self.pixels() {
+            if !(c.x.is_finite() && c.y.is_finite() && c.z.is_finite()) {
+                stats.invalid += 1;
+                continue;
+            }
+            let l = tonemap::luminance(c);
+            stats.min_luminance = stats.min_luminance.min(l);
+            stats.max_luminance = stats.max_luminance.max(l);
+            sum[0] += c.x as f64;
+            sum[1] += c.y as f64;
+            sum[2] += c.z as f64;
+        }
+        let valid = self.radiance.len() - stats.invalid;
+        if valid > 0 {
+            let n = valid as f64;
+            stats.mean = Color::new(
+                (sum[0] / n) as Num,
+                (sum[1] / n) as Num,
+                (sum[2] / n) as Num,
+            );
+        } else {
+            stats.min_luminance = 0.;
+            stats.max_luminance = 0.;
+        }
+        stats
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        assert!(x < self.width && y < self.height);
+        y * self.width + x
+    }
+}
+
+fn average(radiance: Color, samples: u32) -> Color {
+    if samples == 0 {
+        Color::zeros()
+    } else {
+        radiance / samples as Num
+    }
+}
@@ -0,0 +1,325 @@
+// Writers for floating point image formats, which keep radiance above 1 for compositing
+
+use crate::vec3::Color;
+use std::io;
+use std::io::Write;
+
+/// Sample type of the channels in an OpenEXR file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExrPrecision {
+    Half,
+    Float,
+}
+
+/// Writes Radiance RGBE, run-length encoding each scanline. `pixels` is row-major from the top.
+pub fn write_rgbe<W: Write>(
+    mut writer: W,
+    width: usize,
+    height: usize,
+    pixels: &[Color],
+) -> io::Result<()> {
+    assert_eq!(pixels.len(), width * height);
+    write!(
+        writer,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        height, width
+    )?;
+
+    let mut components = vec![vec![0u8; width]; 4];
+    for row in pixels.chunks_exact(width) {
+        // The RLE scheme can only describe widths in [8, 32767]
+        if !(8..32768).contains(&width) {
+            for &c in row {
+                writer.write_all(&to_rgbe(c))?;
+            }
+            continue;
+        }
+
+        for (i, &c) in row.iter().enumerate() {
+            for (component, byte) in components.iter_mut().zip(to_rgbe(c)) {
+                component[i] = byte;
+            }
+        }
+        writer.write_all(&[2, 2, (width >> 8) as u8, width as u8])?;
+        for component in &components {
+            write_rle(&mut writer, component)?;
+        }
+    }
+    Ok(())
+}
+
+// Largest value RGBE can represent, a mantissa of 255/256 with the exponent at its top of 127
+const RGBE_MAX: f32 = 255. / 256. * (1u128 << 127) as f32;
+
+// Shared exponent encoding, see Greg Ward's "Real Pixels" in Graphics Gems II
+fn to_rgbe(c: Color) -> [u8; 4] {
+    let (r, g, b) = (c.x.max(0.), c.y.max(0.), c.z.max(0.));
+    let v = r.max(g).max(b);
+    if !v.is_finite() || v < 1e-32 {
+        return [0; 4];
+    }
+    // Brighter channels saturate at 255 rather than the exponent wrapping around to a tiny value
+    let v = v.min(RGBE_MAX);
+
+    // `v = mantissa * 2^exponent` with the mantissa in [0.5, 1)
+    let mut exponent = v.log2().floor() as i32 + 1;
+    let mut mantissa = v / 2f32.powi(exponent);
+    if mantissa >= 1. {
+        mantissa /= 2.;
+        exponent += 1;
+    } else if mantissa < 0.5 {
+        mantissa *= 2.;
+        exponent -= 1;
+    }
+    let scale = mantissa * 256. / v;
+
+    [
+        (r * scale) as u8,
+        (g * scale) as u8,
+        (b * scale) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+// Runs of at least three equal bytes become `128 + count, byte`, the rest literal `count, bytes..`
+fn write_rle<W: Write>(writer: &mut W, data: &[u8]) -> io::Result<()> {
+    const MIN_RUN: usize = 3;
+    let mut i = 0;
+    while i < data.len() {
+        // Find the next run long enough to be worth encoding
+        let mut run_start = i;
+        let mut run_len = 0;
+        while run_start < data.len() {
+            run_len = data[run_start..]
+                .iter()
+                .take(127)
+                .take_while(|&&b| b == data[run_start])
+                .count();
+            if run_len >= MIN_RUN {
+                break;
+            }
+            run_start += run_len;
+        }
+
+        for literal in data[i..run_start].chunks(128) {
+            writer.write_all(&[literal.len() as u8])?;
+            writer.write_all(literal)?;
+        }
+        if run_start < data.len() {
+            writer.write_all(&[128 + run_len as u8, data[run_start]])?;
+            i = run_start + run_len;
+        } else {
+            i = run_start;
+        }
+    }
+    Ok(())
+}
+
+/// Writes an uncompressed single-part scanline OpenEXR file with R, G and B channels.
+pub fn write_exr<W: Write>(
+    mut writer: W,
+    width: usize,
+    height: usize,
+    pixels: &[Color],
+    precision: ExrPrecision,
+) -> io::Result<()> {
+    assert_eq!(pixels.len(), width * height);
+    let (pixel_type, sample_size) = match precision {
+        ExrPrecision::Half => (1u32, 2),
+        ExrPrecision::Float => (2u32, 4),
+    };
+    let (max_x, max_y) = (width as i32 - 1, height as i32 - 1);
+
+    let mut header = vec![];
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]);
+    header.extend_from_slice(&2u32.to_le_bytes());
+
+    // Channels must be listed in alphabetical order
+    let mut channels = vec![];
+    for name in ["B", "G", "R"] {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        channels.extend_from_slice(&pixel_type.to_le_bytes());
+        // pLinear and reserved bytes, then x and y sampling
+        channels.extend_from_slice(&[0; 4]);
+        channels.extend_from_slice(&1u32.to_le_bytes());
+        channels.extend_from_slice(&1u32.to_le_bytes());
+    }
+    channels.push(0);
+    attribute(&mut header, "channels", "chlist", &channels);
+    attribute(&mut header, "compression", "compression", &[0]);
+    let window: Vec<u8> = [0, 0, max_x, max_y]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    header.push(0);
+    writer.write_all(&header)?;
+
+    // Offset table, one chunk per scanline
+    let chunk_size = 8 + 3 * width * sample_size;
+    let table_end = header.len() + 8 * height;
+    for y in 0..height {
+        writer.write_all(&((table_end + y * chunk_size) as u64).to_le_bytes())?;
+    }
+
+    let mut chunk = Vec::with_capacity(chunk_size);
+    for (y, row) in pixels.chunks_exact(width).enumerate() {
+        chunk.clear();
+        chunk.extend_from_slice(&(y as i32).to_le_bytes());
+        chunk.extend_from_slice(&((chunk_size - 8) as u32).to_le_bytes());
+        for channel in [|c: &Color| c.z, |c: &Color| c.y, |c: &Color| c.x] {
+            for c in row {
+                match precision {
+                    ExrPrecision::Half => {
+                        chunk.extend_from_slice(&to_half(channel(c)).to_le_bytes())
+                    }
+                    ExrPrecision::Float => chunk.extend_from_slice(&channel(c).to_le_bytes()),
+                }
+            }
+        }
+        writer.write_all(&chunk)?;
+    }
+    Ok(())
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+// IEEE 754 binary16, rounding to nearest even
+fn to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // Infinity, or a quiet NaN
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if half_exponent <= 0 {
+        // Subnormal half, or too small and flushed to zero
+        if half_exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - half_exponent) as u32;
+        let round_bit = 1 << (shift - 1);
+        let mut half = mantissa >> shift;
+        if mantissa & round_bit != 0 && mantissa & (3 * round_bit - 1) != 0 {
+            half += 1;
+        }
+        return sign | half as u16;
+    }
+
+    // A carry out of the mantissa correctly bumps the exponent, up to infinity
+    let mut half = ((half_exponent as u32) << 10) | (mantissa >> 13);
+    let rest = mantissa & 0x1fff;
+    if rest > 0x1000 || (rest == 0x1000 && half & 1 == 1) {
+        half += 1;
+    }
+    sign | half as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgbe_saturates_past_the_largest_exponent() {
+        assert_eq!(to_rgbe(Color::new(1., 0.5, 0.)), [128, 64, 0, 129]);
+        assert_eq!(to_rgbe(Color::new(f32::MAX, 0., 0.)), [255, 0, 0, 255]);
+        assert_eq!(
+            to_rgbe(Color::from_elem(2f32.powi(127))),
+            [255, 255, 255, 255]
+        );
+    }
+
+    #[test]
+    fn half_floats_round_to_nearest_even() {
+        assert_eq!(to_half(1.), 0x3c00);
+        assert_eq!(to_half(-2.), 0xc000);
+        assert_eq!(to_half(65504.), 0x7bff);
+        assert_eq!(to_half(1e-8), 0);
+        assert_eq!(to_half(-1e-8), 0x8000);
+        // Halfway between two halves, to the even one
+        assert_eq!(to_half(1. + 2f32.powi(-11)), 0x3c00);
+        assert_eq!(to_half(1. + 3. * 2f32.powi(-11)), 0x3c02);
+        assert_eq!(to_half(65520.), 0x7c00);
+        // Subnormals, the smallest one and the ties on either side of it
+        assert_eq!(to_half(2f32.powi(-24)), 0x0001);
+        assert_eq!(to_half(2f32.powi(-25)), 0);
+        assert_eq!(to_half(3. * 2f32.powi(-25)), 0x0002);
+        assert_eq!(to_half(f32::INFINITY), 0x7c00);
+        assert_eq!(to_half(f32::NAN) & 0x7e00, 0x7e00);
+    }
+
+    // Inverse of `write_rle`
+    fn read_rle(mut data: &[u8]) -> Vec<u8> {
+        let mut out = vec![];
+        while let [count, rest @ ..] = data {
+            if *count > 128 {
+                out.extend(std::iter::repeat_n(rest[0], *count as usize - 128));
+                data = &rest[1..];
+            } else {
+                assert!(*count > 0);
+                out.extend_from_slice(&rest[..*count as usize]);
+                data = &rest[*count as usize..];
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn rle_decodes_to_its_input() {
+        let mut inputs = vec![
+            vec![],
+            vec![7],
+            vec![1, 1],
+            vec![1, 1, 1],
+            vec![5; 300],
+            (0..=255).collect(),
+            (0..1000).map(|i| (i / 7 % 3) as u8).collect(),
+        ];
+        // Runs of every length up to past the longest packet, between literals
+        inputs.push(
+            (0..200u32)
+                .flat_map(|n| {
+                    [n as u8, n as u8 ^ 0xff]
+                        .into_iter()
+                        .chain(std::iter::repeat_n(n as u8, n as usize))
+                })
+                .collect(),
+        );
+        for input in inputs {
+            let mut encoded = vec![];
+            write_rle(&mut encoded, &input).unwrap();
+            assert_eq!(read_rle(&encoded), input);
+        }
+    }
+}
@@ -1,34 +1,32 @@
+use crate::aabb::Aabb;
 use crate::material::Material;
 use crate::ray::Ray;
-use crate::vec3::{Color, Point3, Vector3};
+use crate::vec3::{Point3, Vector3};
 use crate::Num;
+use rand::RngCore;
 use std::ops::Range;
 
 #[derive(Clone, Copy)]
-pub struct HitRecord {
+pub struct HitRecord<'a> {
     pub p: Point3,
     pub normal: Vector3,
     pub t: Num,
-    pub mat: Material,
+    // Surface coordinates for texture lookups
+    pub u: Num,
+    pub v: Num,
+    pub mat: &'a Material,
     pub front_face: bool,
 }
 
-impl Default for HitRecord {
-    fn default() -> Self {
-        Self {
-            p: Point3::default(),
-            normal: Vector3::default(),
-            t: Num::default(),
-            mat: Material::Lambertian {
-                albedo: Color::default(),
-            },
-            front_face: false,
-        }
-    }
-}
-
-impl HitRecord {
-    pub(crate) fn new(ray: Ray, outward_normal: Vector3, p: Point3, t: Num, mat: Material) -> Self {
+impl<'a> HitRecord<'a> {
+    pub fn new(
+        ray: Ray,
+        outward_normal: Vector3,
+        p: Point3,
+        t: Num,
+        (u, v): (Num, Num),
+        mat: &'a Material,
+    ) -> Self {
         let front_face = ray.direction.dot(outward_normal) < 0.;
         let normal = if front_face {
             outward_normal
@@ -39,6 +37,8 @@ impl HitRecord {
             p,
             normal,
             t,
+            u,
+            v,
             mat,
             front_face,
         }
@@ -46,5 +46,8 @@ impl HitRecord {
 }
 
 pub trait Hittable: Sync + Send {
-    fn hit(&self, ray: Ray, range: Range<Num>) -> Option<HitRecord>;
+    fn hit(&self, ray: Ray, range: Range<Num>, rng: &mut dyn RngCore) -> Option<HitRecord<'_>>;
+
+    /// Box enclosing the object over the whole `time` interval, or `None` if it is unbounded.
+    fn bounding_box(&self, time: Range<Num>) -> Option<Aabb>;
 }
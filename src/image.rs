@@ -8,7 +8,7 @@ pub struct Image {
 }
 
 impl Image {
-    pub(crate) fn from_width(aspect_ratio: Num, width: usize) -> Self {
+    pub fn from_width(aspect_ratio: Num, width: usize) -> Self {
         Self {
             aspect_ratio,
             width,
@@ -0,0 +1,381 @@
+use crate::hittable::HitRecord;
+use crate::light::Light;
+use crate::material::Scatter;
+use crate::pdf::Pdf;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::{Color, Point3};
+use crate::{Num, SceneView};
+use rand::Rng;
+use std::fmt;
+use std::str::FromStr;
+
+/// Rendering algorithm, estimating the light arriving at the camera along a ray.
+pub trait Integrator: Sync {
+    fn radiance(&self, ray: Ray, scene: &SceneView, sampler: &mut dyn Sampler) -> Color;
+}
+
+/// Path tracer that only follows the directions the materials scatter into, as in the books.
+///
+/// Paths are ended at random by Russian roulette once they are `roulette_depth` bounces long,
+/// the more likely the less light they still carry, and the survivors are weighted up to make
+/// up for the ones ended. `max_depth` only caps the length of the rare paths that keep going.
+pub struct PathTracer {
+    pub max_depth: usize,
+    pub roulette_depth: usize,
+}
+
+/// Path tracer that also samples the lights directly at every diffuse bounce, see
+/// [`SceneView::lights`].
+///
+/// Light found by a shadow ray towards a random point on the lights and light found by the
+/// scattered ray are weighed by multiple importance sampling, so that each counts where it does
+/// best. Paths end as in [`PathTracer`].
+pub struct NeePathTracer {
+    pub max_depth: usize,
+    pub roulette_depth: usize,
+}
+
+/// Whitted-style ray tracer: mirrors and glass are followed recursively, diffuse surfaces only
+/// see the lights through shadow rays, plus the background above them as ambient light.
+pub struct Whitted {
+    pub max_depth: usize,
+}
+
+/// Fraction of the hemisphere above the first hit that's unoccluded within `distance`, cosine
+/// weighted. Misses are white.
+pub struct AmbientOcclusion {
+    pub distance: Num,
+}
+
+/// Shows a single property of the first hit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugShading {
+    // Color of the material, the attenuation of what it scatters or what it emits
+    Flat,
+    // Outward normal mapped from `[-1, 1]` to `[0, 1]`
+    Normal,
+    // Distance from the camera in scene units, scale it into view with the exposure
+    Depth,
+}
+
+/// Integrators selectable by name, see [`IntegratorKind::build`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IntegratorKind {
+    Path,
+    Nee,
+    Whitted,
+    AmbientOcclusion { distance: Num },
+    Debug(DebugShading),
+}
+
+impl IntegratorKind {
+    /// The integrator, following paths of up to `max_depth` bounces if it bounces at all, and
+    /// playing Russian roulette with paths past `roulette_depth` bounces if it traces paths.
+    pub fn build(self, max_depth: usize, roulette_depth: usize) -> Box<dyn Integrator> {
+        match self {
+            IntegratorKind::Path => Box::new(PathTracer {
+                max_depth,
+                roulette_depth,
+            }),
+            IntegratorKind::Nee => Box::new(NeePathTracer {
+                max_depth,
+                roulette_depth,
+            }),
+            IntegratorKind::Whitted => Box::new(Whitted { max_depth }),
+            IntegratorKind::AmbientOcclusion { distance } => {
+                Box::new(AmbientOcclusion { distance })
+            }
+            IntegratorKind::Debug(shading) => Box::new(shading),
+        }
+    }
+}
+
+impl Integrator for PathTracer {
+    fn radiance(&self, mut ray: Ray, scene: &SceneView, mut sampler: &mut dyn Sampler) -> Color {
+        let mut radiance = Color::zeros();
+        // Fraction of the light arriving along `ray` that reaches the camera
+        let mut throughput = Color::from_elem(1.);
+        for depth in 0..self.max_depth {
+            let Some(rec) = scene.world.hit(ray, 0.0001..Num::MAX, &mut sampler) else {
+                radiance += throughput * scene.background.color(ray);
+                break;
+            };
+            radiance += throughput * rec.mat.emitted();
+
+            match rec.mat.scatter(ray, rec, &mut sampler) {
+                None => break,
+                Some(Scatter::Specular {
+                    attenuation,
+                    ray: scattered,
+                }) => {
+                    throughput *= attenuation;
+                    ray = scattered;
+                }
+                Some(Scatter::Diffuse { attenuation, pdf }) => {
+                    let scattered = Ray::from(rec.p, pdf.generate(&mut sampler), ray.time);
+                    let pdf_value = pdf.value(scattered.direction);
+                    if pdf_value <= 0. {
+                        break;
+                    }
+                    let scattering_pdf = rec.mat.scattering_pdf(&rec, scattered);
+                    throughput *= attenuation * scattering_pdf / pdf_value;
+                    ray = scattered;
+                }
+            }
+            if !roulette(&mut throughput, depth, self.roulette_depth, sampler) {
+                break;
+            }
+        }
+        radiance
+    }
+}
+
+impl Integrator for NeePathTracer {
+    fn radiance(&self, mut ray: Ray, scene: &SceneView, mut sampler: &mut dyn Sampler) -> Color {
+        let mut radiance = Color::zeros();
+        let mut throughput = Color::from_elem(1.);
+        // The diffuse surface point `ray` leaves from and the density its direction was sampled
+        // with, for weighting the emitter it hits against light sampling at that point
+        let mut bounce: Option<(Point3, Num)> = None;
+        for depth in 0..self.max_depth {
+            let Some(rec) = scene.world.hit(ray, 0.0001..Num::MAX, &mut sampler) else {
+                radiance += throughput * scene.background.color(ray);
+                break;
+            };
+
+            let mut emitted = rec.mat.emitted();
+            if let Some((origin, scatter_pdf)) = bounce {
+                if emitted.length_squared() > 0. {
+                    let light_pdf = scene.lights.pdf_value(origin, ray.direction);
+                    emitted *= power_heuristic(scatter_pdf, light_pdf);
+                }
+            }
+            radiance += throughput * emitted;
+
+            match rec.mat.scatter(ray, rec, &mut sampler) {
+                None => break,
+                Some(Scatter::Specular {
+                    attenuation,
+                    ray: scattered,
+                }) => {
+                    throughput *= attenuation;
+                    ray = scattered;
+                    bounce = None;
+                }
+                Some(Scatter::Diffuse { attenuation, pdf }) => {
+                    // The scattered ray can still find the lights unless this is the last bounce
+                    let last = depth + 1 == self.max_depth;
+                    radiance += throughput
+                        * sample_lights(
+                            ray,
+                            &rec,
+                            attenuation,
+                            (!last).then_some(&pdf),
+                            scene,
+                            &mut sampler,
+                        );
+
+                    let scattered = Ray::from(rec.p, pdf.generate(&mut sampler), ray.time);
+                    let pdf_value = pdf.value(scattered.direction);
+                    if pdf_value <= 0. {
+                        break;
+                    }
+                    let scattering_pdf = rec.mat.scattering_pdf(&rec, scattered);
+                    throughput *= attenuation * scattering_pdf / pdf_value;
+                    ray = scattered;
+                    bounce = Some((rec.p, pdf_value));
+                }
+            }
+            if !roulette(&mut throughput, depth, self.roulette_depth, sampler) {
+                break;
+            }
+        }
+        radiance
+    }
+}
+
+// Russian roulette after bounce `depth`, counted from 0: once past `roulette_depth` bounces the
+// path survives with a probability following its throughput, which is divided by that
+// probability so the expected contribution stays the same. Returns whether the path goes on.
+fn roulette(
+    throughput: &mut Color,
+    depth: usize,
+    roulette_depth: usize,
+    sampler: &mut dyn Sampler,
+) -> bool {
+    if depth < roulette_depth {
+        return true;
+    }
+    // Capped so that bright paths still end eventually
+    let survival = throughput.max_element().min(0.95);
+    if survival <= 0. || sampler.get_1d() >= survival {
+        return false;
+    }
+    *throughput /= survival;
+    true
+}
+
+impl Integrator for Whitted {
+    fn radiance(&self, ray: Ray, scene: &SceneView, mut sampler: &mut dyn Sampler) -> Color {
+        whitted(ray, scene, self.max_depth, &mut sampler)
+    }
+}
+
+fn whitted<R: Rng>(ray: Ray, scene: &SceneView, depth: usize, rng: &mut R) -> Color {
+    if depth == 0 {
+        return Color::zeros();
+    }
+    let Some(rec) = scene.world.hit(ray, 0.0001..Num::MAX, rng) else {
+        return scene.background.color(ray);
+    };
+
+    let emitted = rec.mat.emitted();
+    match rec.mat.scatter(ray, rec, rng) {
+        None => emitted,
+        Some(Scatter::Specular { attenuation, ray }) => {
+            emitted + attenuation * whitted(ray, scene, depth - 1, rng)
+        }
+        Some(Scatter::Diffuse { attenuation, .. }) => {
+            let direct = sample_lights(ray, &rec, attenuation, None, scene, rng);
+            let ambient = scene
+                .background
+                .color(Ray::from(rec.p, rec.normal, ray.time));
+            emitted + direct + attenuation * ambient
+        }
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(&self, ray: Ray, scene: &SceneView, mut sampler: &mut dyn Sampler) -> Color {
+        let Some(rec) = scene.world.hit(ray, 0.0001..Num::MAX, &mut sampler) else {
+            return Color::from_elem(1.);
+        };
+        let probe = Ray::from(
+            rec.p,
+            Pdf::Cosine { w: rec.normal }.generate(&mut sampler),
+            ray.time,
+        );
+        // Cosine directions are unit vectors, so `t` is the distance
+        match scene.world.hit(probe, 0.0001..self.distance, &mut sampler) {
+            Some(_) => Color::zeros(),
+            None => Color::from_elem(1.),
+        }
+    }
+}
+
+impl Integrator for DebugShading {
+    fn radiance(&self, ray: Ray, scene: &SceneView, mut sampler: &mut dyn Sampler) -> Color {
+        let Some(rec) = scene.world.hit(ray, 0.0001..Num::MAX, &mut sampler) else {
+            return match self {
+                DebugShading::Flat => scene.background.color(ray),
+                _ => Color::zeros(),
+            };
+        };
+        match self {
+            DebugShading::Flat => match rec.mat.scatter(ray, rec, &mut sampler) {
+                Some(Scatter::Specular { attenuation, .. })
+                | Some(Scatter::Diffuse { attenuation, .. }) => attenuation,
+                None => rec.mat.emitted(),
+            },
+            DebugShading::Normal => {
+                let outward = if rec.front_face {
+                    rec.normal
+                } else {
+                    -rec.normal
+                };
+                0.5 * (outward + Color::from_elem(1.))
+            }
+            DebugShading::Depth => Color::from_elem(rec.t * ray.direction.length()),
+        }
+    }
+}
+
+// Light reaching `rec` straight from a random point on the lights and scattered back along
+// `ray`, weighted against finding the same light by sampling `scatter_pdf` if given
+fn sample_lights<R: Rng>(
+    ray: Ray,
+    rec: &HitRecord,
+    attenuation: Color,
+    scatter_pdf: Option<&Pdf>,
+    scene: &SceneView,
+    rng: &mut R,
+) -> Color {
+    if scene.lights.0.is_empty() {
+        return Color::zeros();
+    }
+    let lights = Pdf::Light {
+        light: scene.lights,
+        origin: rec.p,
+    };
+    let shadow = Ray::from(rec.p, lights.generate(rng), ray.time);
+    let light_pdf = lights.value(shadow.direction);
+    let scattering_pdf = rec.mat.scattering_pdf(rec, shadow);
+    if light_pdf <= 0. || scattering_pdf <= 0. {
+        return Color::zeros();
+    }
+
+    // Whatever the shadow ray hits first, an occluder emits nothing
+    let Some(light) = scene.world.hit(shadow, 0.0001..Num::MAX, rng) else {
+        return Color::zeros();
+    };
+    let weight = match scatter_pdf {
+        Some(pdf) => power_heuristic(light_pdf, pdf.value(shadow.direction)),
+        None => 1.,
+    };
+    attenuation * scattering_pdf * light.mat.emitted() * weight / light_pdf
+}
+
+// Multiple importance sampling weight of the strategy sampling with density `pdf` against one
+// sampling with `other`
+fn power_heuristic(pdf: Num, other: Num) -> Num {
+    let (a, b) = (pdf * pdf, other * other);
+    if a + b > 0. {
+        a / (a + b)
+    } else {
+        0.
+    }
+}
+
+impl fmt::Display for IntegratorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegratorKind::Path => f.write_str("path"),
+            IntegratorKind::Nee => f.write_str("nee"),
+            IntegratorKind::Whitted => f.write_str("whitted"),
+            IntegratorKind::AmbientOcclusion { distance } => write!(f, "ao:{}", distance),
+            IntegratorKind::Debug(DebugShading::Flat) => f.write_str("flat"),
+            IntegratorKind::Debug(DebugShading::Normal) => f.write_str("normal"),
+            IntegratorKind::Debug(DebugShading::Depth) => f.write_str("depth"),
+        }
+    }
+}
+
+impl FromStr for IntegratorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, distance) = match s.split_once(':') {
+            Some((name, distance)) => (name, Some(distance)),
+            None => (s, None),
+        };
+        match (name, distance) {
+            ("path", None) => Ok(IntegratorKind::Path),
+            ("nee", None) => Ok(IntegratorKind::Nee),
+            ("whitted", None) => Ok(IntegratorKind::Whitted),
+            ("ao", None) => Ok(IntegratorKind::AmbientOcclusion { distance: 1. }),
+            ("ao", Some(distance)) => distance
+                .parse()
+                .map(|distance| IntegratorKind::AmbientOcclusion { distance })
+                .map_err(|_| format!("invalid occlusion distance `{}`", distance)),
+            ("flat", None) => Ok(IntegratorKind::Debug(DebugShading::Flat)),
+            ("normal", None) => Ok(IntegratorKind::Debug(DebugShading::Normal)),
+            ("depth", None) => Ok(IntegratorKind::Debug(DebugShading::Depth)),
+            _ => Err(format!(
+                "unknown integrator `{}`, expected path, nee, whitted, ao[:DISTANCE], flat, \
+                 normal or depth",
+                s
+            )),
+        }
+    }
+}
@@ -0,0 +1,345 @@
+//! Ray tracer following Peter Shirley's "Ray Tracing in One Weekend" series.
+//!
+//! Build a [`World`] of [`Hittable`] shapes with [`Material`]s, or load one with [`Scene::load`],
+//! wrap it in a [`Bvh`] and [`render`] it through a [`Camera`] into a [`Film`], whose pixels can
+//! be inspected, summarized or saved with [`Film::save`].
+
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+
+pub use crate::background::Background;
+pub use crate::bvh::Bvh;
+pub use crate::camera::{Camera, Lens};
+pub use crate::checkpoint::Checkpoint;
+pub use crate::film::{Film, FilmStats};
+pub use crate::hittable::{HitRecord, Hittable};
+pub use crate::image::Image;
+pub use crate::integrator::{Integrator, IntegratorKind};
+pub use crate::light::{Light, Lights};
+pub use crate::material::{Material, Scatter};
+pub use crate::pdf::Pdf;
+pub use crate::ray::Ray;
+pub use crate::sampler::{Sampler, SamplerKind};
+pub use crate::scene::{Scene, SceneError};
+pub use crate::texture::Texture;
+pub use crate::tiles::{Progress, Tile, TileOrder};
+pub use crate::tonemap::DisplayTransform;
+pub use crate::vec3::{Color, Point3, Vector3};
+pub use crate::world::World;
+
+pub mod aabb;
+pub mod background;
+pub mod bvh;
+pub mod camera;
+pub mod checkpoint;
+pub mod film;
+pub mod hdr;
+pub mod hittable;
+pub mod image;
+pub mod integrator;
+pub mod light;
+pub mod material;
+pub mod medium;
+pub mod obj;
+pub mod output;
+pub mod pdf;
+pub mod perlin;
+pub mod ray;
+pub mod rng;
+pub mod sampler;
+pub mod scene;
+pub mod scenes;
+pub mod shapes;
+pub mod texture;
+pub mod tiles;
+pub mod tonemap;
+pub mod transform;
+pub mod triangle;
+pub mod vec3;
+pub mod world;
+
+pub type Num = f32;
+
+pub fn translate_color(pixel_color: Color, samples: usize) -> [u8; 3] {
+    DisplayTransform::default().rgb8(pixel_color / samples as Num)
+}
+
+pub fn write_color<W: io::Write>(
+    writer: &mut W,
+    pixel_color: Color,
+    samples: usize,
+) -> io::Result<()> {
+    let [ir, ig, ib] = translate_color(pixel_color, samples);
+
+    writeln!(writer, "{} {} {}", ir, ig, ib)
+}
+
+/// The parts of a scene rays interact with.
+#[derive(Clone, Copy)]
+pub struct SceneView<'a> {
+    pub world: &'a dyn Hittable,
+    /// Emitters that diffuse bounces aim part of their rays at, each also part of `world`
+    pub lights: &'a Lights,
+    pub background: &'a Background,
+}
+
+/// Knobs of a render that aren't part of the scene.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderSettings {
+    /// Samples per pixel
+    pub samples: usize,
+    /// Seed of the random streams, see [`rng::sample_stream`]
+    pub seed: u64,
+    /// Generator of the random numbers of each sample
+    pub sampler: SamplerKind,
+    /// Samples per pixel of the whole render when this call only takes the first `samples` of
+    /// them, as one of several passes, so that the sampler spreads them out the same way
+    pub total_samples: Option<usize>,
+    /// Side of the square tiles handed out to the render threads, in pixels
+    pub tile_size: usize,
+    pub tile_order: TileOrder,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            samples: 100,
+            seed: 0,
+            sampler: SamplerKind::Sobol,
+            total_samples: None,
+            tile_size: 32,
+            tile_order: TileOrder::Scanline,
+        }
+    }
+}
+
+/// Renders `scene` as seen by `camera` with `integrator`, taking `settings.samples` samples for
+/// each pixel.
+/// Every sample takes its random numbers from `settings.sampler` seeded with `settings.seed`, and
+/// they only depend on the pixel and the sample index, so the film is the same for any thread
+/// count and tile order. `progress` is called after each finished tile.
+pub fn render<F>(
+    scene: &SceneView,
+    integrator: &dyn Integrator,
+    image: Image,
+    camera: Camera,
+    settings: &RenderSettings,
+    progress: F,
+) -> Film
+where
+    F: Fn(&Progress) + Sync,
+{
+    let mut film = Film::new(image.width, image.height);
+    let cancel = AtomicBool::new(false);
+    render_into(
+        scene, integrator, &camera, settings, &mut film, &cancel, progress,
+    );
+    film
+}
+
+/// Adds samples to every pixel of `film` until it holds `settings.samples` of them. Sample `n` of
+/// a pixel is the same whether the film is rendered at once or over several calls.
+///
+/// Once `cancel` is set no further tiles are started, the film then keeps what the finished tiles
+/// added. Returns `false` if it was cancelled before covering the whole image.
+pub fn render_into<F>(
+    scene: &SceneView,
+    integrator: &dyn Integrator,
+    camera: &Camera,
+    settings: &RenderSettings,
+    film: &mut Film,
+    cancel: &AtomicBool,
+    progress: F,
+) -> bool
+where
+    F: Fn(&Progress) + Sync,
+{
+    let (width, height) = (film.width(), film.height());
+    let tiles = tiles::tiles(width, height, settings.tile_size, settings.tile_order);
+    let start = Instant::now();
+    let state = Mutex::new((
+        film,
+        Progress {
+            tiles_done: 0,
+            tiles_total: tiles.len(),
+            pixels_done: 0,
+            pixels_total: width * height,
+            samples: 0,
+            elapsed: Duration::ZERO,
+        },
+    ));
+
+    // Threads take the next tile in line rather than splitting the list, to keep to the order
+    let next = AtomicUsize::new(0);
+    (0..rayon::current_num_threads())
+        .into_par_iter()
+        .for_each(|_| {
+            while let Some(&tile) = tiles.get(next.fetch_add(1, Ordering::Relaxed)) {
+                if cancel.load(Ordering::Relaxed) {
+                    break;
+                }
+                let mut pixels: Vec<_> = {
+                    let film = &state.lock().unwrap().0;
+                    tile_pixels(tile)
+                        .map(|(x, y)| (film.accumulated(x, y), film.sample_count(x, y)))
+                        .collect()
+                };
+                let taken = render_tile(
+                    scene,
+                    integrator,
+                    camera,
+                    settings,
+                    (width, height),
+                    tile,
+                    &mut pixels,
+                );
+
+                let mut state = state.lock().unwrap();
+                let (film, status) = &mut *state;
+                for ((x, y), (radiance, samples)) in tile_pixels(tile).zip(pixels) {
+                    film.set_accumulated(x, y, radiance, samples);
+                }
+                status.tiles_done += 1;
+                status.pixels_done += tile.width * tile.height;
+                status.samples += taken;
+                status.elapsed = start.elapsed();
+                progress(status);
+            }
+        });
+
+    let status = state.into_inner().unwrap().1;
+    status.tiles_done == status.tiles_total
+}
+
+// Columns and rows of the pixels in `tile`, row-major
+fn tile_pixels(tile: Tile) -> impl Iterator<Item = (usize, usize)> {
+    (tile.y..tile.y + tile.height)
+        .flat_map(move |y| (tile.x..tile.x + tile.width).map(move |x| (x, y)))
+}
+
+// Adds samples to the summed radiance and sample count of each pixel in `tile` until it has
+// `settings.samples`, returning the number of samples taken. Accumulating sample by sample
+// onto the previous sum keeps the result independent of how the samples are split over calls.
+fn render_tile(
+    scene: &SceneView,
+    integrator: &dyn Integrator,
+    camera: &Camera,
+    settings: &RenderSettings,
+    (width, height): (usize, usize),
+    tile: Tile,
+    pixels: &mut [(Color, u32)],
+) -> u64 {
+    let mut taken = 0;
+    let mut sampler = settings.sampler.build(
+        settings.seed,
+        settings.total_samples.unwrap_or(settings.samples),
+    );
+    for ((i, y), (radiance, samples)) in tile_pixels(tile).zip(pixels) {
+        // The camera counts rows from the bottom
+        let j = height - 1 - y;
+        let index = (y * width + i) as u64;
+        while (*samples as usize) < settings.samples {
+            sampler.start_pixel_sample(index, *samples);
+            let [du, dv] = sampler.get_2d();
+            let u = (i as Num + du) / (width - 1) as Num;
+            let v = (j as Num + dv) / (height - 1) as Num;
+            let r = camera.cast_ray(u, v, sampler.as_mut());
+            *radiance += integrator.radiance(r, scene, sampler.as_mut());
+            *samples += 1;
+            taken += 1;
+        }
+    }
+    taken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // Summed radiance and sample count of every pixel, bit for bit
+    fn film_bits(film: &Film) -> Vec<[u32; 4]> {
+        (0..film.height())
+            .flat_map(|y| (0..film.width()).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let c = film.accumulated(x, y);
+                [
+                    c.x.to_bits(),
+                    c.y.to_bits(),
+                    c.z.to_bits(),
+                    film.sample_count(x, y),
+                ]
+            })
+            .collect()
+    }
+
+    // The smoke scene, for media as well as surfaces and lights, rendered on `threads` threads
+    fn render_smoke(threads: usize, settings: &RenderSettings) -> Film {
+        let scene = scenes::find("cornell_smoke")
+            .unwrap()
+            .build(&mut StdRng::seed_from_u64(0));
+        let world = Bvh::new(scene.world, scene.camera.exposure());
+        let view = SceneView {
+            world: &world,
+            lights: &scene.lights,
+            background: &scene.background,
+        };
+        let integrator = IntegratorKind::Nee.build(10, 3);
+        let image = Image::from_width(scene.image.aspect_ratio, 24);
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(|| {
+                render(
+                    &view,
+                    integrator.as_ref(),
+                    image,
+                    scene.camera,
+                    settings,
+                    |_| {},
+                )
+            })
+    }
+
+    #[test]
+    fn film_is_independent_of_threads_and_tiles() {
+        for sampler in [
+            SamplerKind::Independent,
+            SamplerKind::Stratified,
+            SamplerKind::Halton,
+            SamplerKind::Sobol,
+        ] {
+            let settings = RenderSettings {
+                samples: 4,
+                sampler,
+                ..RenderSettings::default()
+            };
+            let reference = film_bits(&render_smoke(1, &settings));
+            for (threads, tile_size, tile_order) in [
+                (4, 32, TileOrder::Scanline),
+                (4, 5, TileOrder::Spiral),
+                (3, 7, TileOrder::Hilbert),
+            ] {
+                let settings = RenderSettings {
+                    tile_size,
+                    tile_order,
+                    ..settings
+                };
+                assert!(
+                    film_bits(&render_smoke(threads, &settings)) == reference,
+                    "{} sampler, {} threads, {}px {:?} tiles",
+                    sampler,
+                    threads,
+                    tile_size,
+                    tile_order
+                );
+            }
+        }
+    }
+}
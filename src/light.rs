@@ -0,0 +1,38 @@
+use crate::vec3::{Point3, Vector3};
+use crate::Num;
+use rand::{Rng, RngCore};
+
+/// Shape that rays can be aimed at, to sample the light it emits directly.
+pub trait Light: Sync + Send {
+    /// Density, per unit solid angle, of [`Light::random`] picking `direction` from `origin`.
+    fn pdf_value(&self, origin: Point3, direction: Vector3) -> Num;
+
+    /// Random direction from `origin` towards a point on the light.
+    fn random(&self, origin: Point3, rng: &mut dyn RngCore) -> Vector3;
+}
+
+/// Lights sampled together, each picked with the same probability.
+pub struct Lights(pub Vec<Box<dyn Light>>);
+
+impl Lights {
+    pub fn add(&mut self, light: Box<dyn Light>) {
+        self.0.push(light)
+    }
+}
+
+impl Light for Lights {
+    fn pdf_value(&self, origin: Point3, direction: Vector3) -> Num {
+        let weight = 1. / self.0.len() as Num;
+        self.0
+            .iter()
+            .map(|l| weight * l.pdf_value(origin, direction))
+            .sum()
+    }
+
+    fn random(&self, origin: Point3, rng: &mut dyn RngCore) -> Vector3 {
+        match self.0.len() {
+            0 => Vector3::new(1., 0., 0.),
+            n => self.0[rng.gen_range(0..n)].random(origin, rng),
+        }
+    }
+}
@@ -1,247 +1,378 @@
-#![allow(dead_code, unused_imports)]
-
-use std::fs::File;
-use std::io;
-use std::io::Write;
-use std::ops::Div;
-
-use rand::{random, Rng, SeedableRng};
-use rayon::prelude::*;
-
-use crate::camera::{Camera, Viewport};
-use crate::hittable::{HitRecord, Hittable};
-use crate::image::Image;
-use crate::material::Material;
-use crate::ray::Ray;
-use crate::shapes::Sphere;
-use crate::vec3::{Color, Point3, Vector3};
-use crate::world::World;
-use rand::prelude::StdRng;
-
-mod camera;
-mod hittable;
-mod image;
-mod material;
-mod ray;
-mod shapes;
-mod vec3;
-mod world;
-
-pub(crate) type Num = f32;
-
-pub fn translate_color(pixel_color: Color, samples: usize) -> [u8; 3] {
-    let scale = 1.0 / (samples as Num);
-    let [r, g, b] = [
-        (pixel_color.x * scale).sqrt(),
-        (pixel_color.y * scale).sqrt(),
-        (pixel_color.z * scale).sqrt(),
-    ];
-    let ir = (255.99 * r.clamp(0.0, 0.99)) as u8;
-    let ig = (255.99 * g.clamp(0.0, 0.99)) as u8;
-    let ib = (255.99 * b.clamp(0.0, 0.99)) as u8;
-
-    [ir, ig, ib]
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rtiow::checkpoint::{self, Checkpoint};
+use rtiow::output::ImageFormat;
+use rtiow::scenes;
+use rtiow::tonemap::{luminance, DisplayTransform, Oetf, ToneMap};
+use rtiow::{
+    render_into, Bvh, Film, Image, IntegratorKind, Num, Progress, RenderSettings, SamplerKind,
+    Scene, SceneView, TileOrder,
+};
+
+use clap::{Args, Parser, Subcommand};
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+fn main() {
+    //https://raytracing.github.io/books/RayTracingInOneWeekend.html
+
+    let cli = Cli::parse();
+    match cli.command {
+        Command::ListScenes => {
+            for scene in scenes::SCENES {
+                println!("{:16}{}", scene.name, scene.description);
+            }
+        }
+        Command::Render(args) => {
+            if let Err(e) = run_render(*args) {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+#[derive(Parser)]
+#[command(version, about = "Ray tracing in one weekend, and the books after it")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
 }
 
-pub(crate) fn write_color<W: io::Write>(writer: &mut W, pixel_color: Color, samples: usize) {
-    let scale = 1.0 / (samples as Num);
-    let [r, g, b] = [
-        (pixel_color.x * scale).sqrt(),
-        (pixel_color.y * scale).sqrt(),
-        (pixel_color.z * scale).sqrt(),
-    ];
-
-    let ir = (255.99 * r.clamp(0.0, 0.99)) as u8;
-    let ig = (255.99 * g.clamp(0.0, 0.99)) as u8;
-    let ib = (255.99 * b.clamp(0.0, 0.99)) as u8;
-
-    writer
-        .write_all(format!("{} {} {}\n", ir, ig, ib).as_ref())
-        .unwrap();
+#[derive(Subcommand)]
+enum Command {
+    /// Render a built-in scene or a scene file
+    Render(Box<RenderArgs>),
+    /// List the built-in scenes
+    ListScenes,
 }
 
-pub(crate) fn ray_color(ray: Ray, world: &World, depth: usize) -> Color {
-    let mut rng = rand::thread_rng();
-    if depth <= 0 {
-        return Color::zeros();
+#[derive(Args)]
+struct RenderArgs {
+    /// Built-in scene to render, see `list-scenes`
+    #[arg(long, default_value = "final_scene")]
+    scene: String,
+    /// TOML scene description to render instead of a built-in scene
+    #[arg(long, conflicts_with = "scene")]
+    scene_file: Option<PathBuf>,
+    /// Image width in pixels, the height follows the scene's aspect ratio
+    #[arg(long)]
+    width: Option<usize>,
+    /// Samples per pixel
+    #[arg(long)]
+    samples: Option<usize>,
+    /// Maximum number of bounces per path, a safety cap for paths that survive Russian roulette
+    #[arg(long)]
+    depth: Option<usize>,
+    /// Bounces before paths can be ended early by Russian roulette
+    #[arg(long, default_value_t = 3)]
+    roulette_depth: usize,
+    /// Rendering algorithm: path, nee (path tracing with light sampling), whitted,
+    /// ao[:DISTANCE] (ambient occlusion), or the flat, normal and depth debug views
+    #[arg(long, default_value_t = IntegratorKind::Nee)]
+    integrator: IntegratorKind,
+    /// Sample generator: independent, stratified, halton or sobol
+    #[arg(long, default_value_t = SamplerKind::Sobol)]
+    sampler: SamplerKind,
+    /// Seed for the random placement in built-in scenes and for the render itself
+    #[arg(long, default_value_t = 0xFACE)]
+    seed: u64,
+    /// Number of render threads, defaults to one per core
+    #[arg(long)]
+    threads: Option<usize>,
+    /// Side of the square tiles the image is split into, in pixels
+    #[arg(long, default_value_t = 32)]
+    tile_size: usize,
+    /// Order the tiles are rendered in: scanline, spiral or hilbert
+    #[arg(long, default_value_t = TileOrder::Scanline)]
+    tile_order: TileOrder,
+    /// Render progressively, in passes adding this many samples per pixel over the whole image
+    #[arg(long)]
+    pass_samples: Option<usize>,
+    /// Stop after this many seconds and save the samples taken so far
+    #[arg(long)]
+    time_limit: Option<f64>,
+    /// Save the image after every this many passes
+    #[arg(long)]
+    snapshot_passes: Option<usize>,
+    /// Save the image after a pass once this many seconds passed since the last save
+    #[arg(long)]
+    snapshot_interval: Option<f64>,
+    /// Keep the accumulated samples in this file, updated after every pass and when stopped
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Continue the render saved in the checkpoint file, with the same scene and settings
+    #[arg(long, requires = "checkpoint")]
+    resume: bool,
+    /// Where to write the image
+    #[arg(short, long, default_value = "final_scene.ppm")]
+    output: PathBuf,
+    /// Image format (ppm, ppm-ascii, png, tga, bmp, hdr, exr or exr-float), by default guessed
+    /// from the output extension
+    #[arg(long)]
+    format: Option<ImageFormat>,
+    /// Exposure adjustment in stops, applied before tone mapping
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    exposure: Num,
+    /// Tone curve: clamp, reinhard, extended-reinhard[:WHITE], filmic or aces
+    #[arg(long, default_value_t = ToneMap::Clamp)]
+    tone_map: ToneMap,
+    /// Transfer function of the display: gamma2 or srgb
+    #[arg(long, default_value_t = Oetf::Gamma2)]
+    oetf: Oetf,
+}
+
+fn run_render(args: RenderArgs) -> Result<(), Box<dyn Error>> {
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
     }
-    if let Some(rec) = world.hit(ray, 0.0001..Num::MAX) {
-        if let Some((attenuation, scattered)) = rec.mat.scatter(ray, rec, &mut rng) {
-            return attenuation * ray_color(scattered, world, depth - 1);
+
+    let mut scene = match &args.scene_file {
+        Some(path) => Scene::load(path)?,
+        None => {
+            let builtin = scenes::find(&args.scene).ok_or_else(|| {
+                format!(
+                    "unknown scene `{}`, see `list-scenes` for the built-in scenes",
+                    args.scene
+                )
+            })?;
+            builtin.build(&mut StdRng::seed_from_u64(args.seed))
         }
-        return Color::zeros();
+    };
+    if let Some(width) = args.width {
+        scene.image = Image::from_width(scene.image.aspect_ratio, width);
     }
+    // The camera maps pixels to `[0, 1]` by dividing by the width and height less one
+    if scene.image.width < 2 || scene.image.height < 2 {
+        return Err(format!(
+            "the image must be at least 2x2 pixels, not {}x{}",
+            scene.image.width, scene.image.height
+        )
+        .into());
+    }
+    let samples = args.samples.unwrap_or(scene.samples);
+    if samples == 0 {
+        return Err("the samples per pixel must be at least 1".into());
+    }
+    let time_limit = args
+        .time_limit
+        .map(|seconds| {
+            Duration::try_from_secs_f64(seconds)
+                .map_err(|err| format!("invalid time limit {seconds}: {err}"))
+        })
+        .transpose()?;
+    let settings = RenderSettings {
+        samples,
+        seed: args.seed,
+        sampler: args.sampler,
+        total_samples: None,
+        tile_size: args.tile_size,
+        tile_order: args.tile_order,
+    };
 
-    // Blue to white gradient if the ray does not hit anything
-    let unit_direction = ray.direction.normalize();
-    let t = 0.5 * (unit_direction.y + 1.0);
-    (1.0 - t) * Color::new(1.0, 1.0, 1.0) + t * Color::new(0.5, 0.7, 1.0)
-}
+    // Fail on an unknown extension before spending time on the render
+    let format = args
+        .format
+        .or_else(|| ImageFormat::from_path(&args.output))
+        .ok_or_else(|| {
+            format!(
+                "cannot tell the image format of {}, use --format",
+                args.output.display()
+            )
+        })?;
+
+    let transform = DisplayTransform {
+        exposure: args.exposure,
+        tone_map: args.tone_map,
+        oetf: args.oetf,
+    };
+
+    // The first Ctrl-C or termination signal stops the render and saves what it has, a second one
+    // quits right away
+    ctrlc::set_handler(|| {
+        if STOP.swap(true, Ordering::Relaxed) {
+            std::process::exit(130);
+        }
+    })?;
+
+    eprintln!("{}x{}", scene.image.width, scene.image.height);
+    let world = Bvh::new(scene.world, scene.camera.exposure());
+    let view = SceneView {
+        world: &world,
+        lights: &scene.lights,
+        background: &scene.background,
+    };
+    let start = Instant::now();
+    // A limit too far ahead to represent is no limit
+    let deadline = time_limit.and_then(|limit| start.checked_add(limit));
+    let pass_samples = args.pass_samples.unwrap_or(settings.samples).max(1);
+    let passes = settings.samples.div_ceil(pass_samples);
+    let max_depth = args.depth.unwrap_or(scene.max_depth);
+    let integrator = args.integrator.build(max_depth, args.roulette_depth);
+    let fingerprint = fingerprint(&args, max_depth, scene.image)?;
+    let mut film = match &args.checkpoint {
+        Some(path) if args.resume => {
+            let checkpoint = Checkpoint::load(path)?;
+            if checkpoint.fingerprint != fingerprint || checkpoint.seed != settings.seed {
+                return Err(format!(
+                    "{} was saved for a different scene or settings",
+                    path.display()
+                )
+                .into());
+            }
+            if args.sampler == SamplerKind::Stratified && checkpoint.samples != settings.samples {
+                return Err(format!(
+                    "{} was saved for {} samples per pixel, which the stratified sampler cannot \
+                     change when resuming",
+                    path.display(),
+                    checkpoint.samples
+                )
+                .into());
+            }
+            checkpoint.film
+        }
+        _ => Film::new(scene.image.width, scene.image.height),
+    };
+    let save_checkpoint = |film: &Film| match &args.checkpoint {
+        Some(path) => Checkpoint {
+            film: film.clone(),
+            seed: settings.seed,
+            samples: settings.samples,
+            fingerprint,
+        }
+        .save(path),
+        None => Ok(()),
+    };
 
-pub(crate) fn render(
-    world: &World,
-    image: Image,
-    camera: Camera,
-    samples: usize,
-    depth: usize,
-) -> io::Result<()> {
-    let mut file = File::create("final_scene.ppm")?;
-    eprintln!("{}x{}", image.width, image.height);
-    file.write_fmt(format_args!("P3\n{} {}\n255\n", image.width, image.height))?;
-
-    let mut im: Vec<Color> = vec![Color::zeros(); image.height * image.width];
-
-    im.par_chunks_exact_mut(image.width)
-        .rev()
-        .enumerate()
-        .for_each(|(j, slice)| {
-            slice.into_par_iter().enumerate().for_each(|(i, pixel)| {
-                for _ in 0..samples {
-                    let u = (i as Num + random::<Num>()) / (image.width - 1) as Num;
-                    let v = (j as Num + random::<Num>()) / (image.height - 1) as Num;
-                    let r = camera.cast_ray(u, v);
-                    *pixel += ray_color(r, world, depth);
+    // Passes already complete in a resumed film have nothing left to add
+    let first_pass = film.min_sample_count() as usize / pass_samples + 1;
+    let mut last_snapshot = start;
+    for pass in first_pass..=passes {
+        let pass_settings = RenderSettings {
+            samples: (pass * pass_samples).min(settings.samples),
+            total_samples: Some(settings.samples),
+            ..settings
+        };
+        render_into(
+            &view,
+            integrator.as_ref(),
+            &scene.camera,
+            &pass_settings,
+            &mut film,
+            &STOP,
+            |progress| {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    STOP.store(true, Ordering::Relaxed);
                 }
-            })
-        });
+                draw_progress(pass, passes, start, progress);
+            },
+        );
+        if STOP.load(Ordering::Relaxed) || pass == passes {
+            break;
+        }
+        save_checkpoint(&film)?;
+
+        let snapshot_due = args.snapshot_passes.is_some_and(|n| pass % n.max(1) == 0)
+            || args
+                .snapshot_interval
+                .is_some_and(|seconds| last_snapshot.elapsed().as_secs_f64() >= seconds);
+        if snapshot_due {
+            film.save(&args.output, Some(format), &transform)?;
+            last_snapshot = Instant::now();
+        }
+    }
+    eprintln!();
+    if STOP.load(Ordering::Relaxed) {
+        eprintln!("Stopped early, saving the samples taken so far");
+    }
+    film.save(&args.output, Some(format), &transform)?;
+    save_checkpoint(&film)?;
 
-    for c in im {
-        let [r, g, b] = translate_color(c, samples);
-        file.write_fmt(format_args!("{} {} {}\n", r, g, b))?;
+    let stats = film.stats();
+    let pixels = film.width() * film.height();
+    eprintln!(
+        "Done in {}, {:.1} samples per pixel, luminance {:.3} mean, {:.3} to {:.3}",
+        format_duration(start.elapsed()),
+        stats.samples as f64 / pixels as f64,
+        luminance(stats.mean),
+        stats.min_luminance,
+        stats.max_luminance
+    );
+    if stats.invalid > 0 {
+        eprintln!("warning: {} pixels are NaN or infinite", stats.invalid);
     }
-    eprintln!("\nDone\n");
     Ok(())
 }
 
-fn main() {
-    //https://raytracing.github.io/books/RayTracingInOneWeekend.html
-
-    const SAMPLES: usize = 500;
-    const MAX_DEPTH: usize = 50;
-    let image = Image::from_width(Camera::ASPECT_RATIO, 1200);
-    let camera = Camera::new(
-        Point3::new(13., 2., 3.),
-        Point3::new(0., 0., 0.),
-        Vector3::new(0., 1., 0.),
-        20.0,
-        0.0 .. 0.001
+// Fingerprint of everything that decides the samples except the seed, which a checkpoint stores
+// by itself. Meshes and textures referenced by a scene file aren't included.
+fn fingerprint(args: &RenderArgs, max_depth: usize, image: Image) -> io::Result<u64> {
+    let mut key = match &args.scene_file {
+        Some(path) => fs::read(path)?,
+        None => args.scene.clone().into_bytes(),
+    };
+    key.extend(
+        format!(
+            "\n{} {}x{} {} {} depth {} roulette {}",
+            env!("CARGO_PKG_VERSION"),
+            image.width,
+            image.height,
+            args.integrator,
+            args.sampler,
+            max_depth,
+            args.roulette_depth
+        )
+        .bytes(),
     );
-    let mut rng = rand::rngs::StdRng::seed_from_u64(0xFACE);
-
-    let _world = World(vec![
-        // Ground
-        Box::new(Sphere::new(
-            Point3::new(0., -100.5, -1.),
-            100.,
-            Material::Lambertian {
-                albedo: Color::new(0.8, 0.8, 0.),
-            },
-        )),
-        //Left
-        Box::new(Sphere::new(
-            Point3::new(-1., 0., -1.),
-            0.5,
-            Material::Dielectric { ir: 1.5 },
-        )),
-        // Inner left
-        Box::new(Sphere::new(
-            Point3::new(-1., 0., -1.),
-            -0.4,
-            Material::Dielectric { ir: 1.5 },
-        )),
-        // Center
-        Box::new(Sphere::new(
-            Point3::new(0., 0., -1.),
-            0.5,
-            Material::Lambertian {
-                albedo: Color::new(0.1, 0.2, 0.5),
-            },
-        )),
-        //Right
-        Box::new(Sphere::new(
-            Point3::new(1., 0., -1.),
-            0.5,
-            Material::Metal {
-                albedo: Color::new(0.8, 0.6, 0.2),
-                fuzz: 0.0,
-            },
-        )),
-    ]);
-    let random_scene = final_scene(&mut rng);
-    render(&random_scene, image, camera, SAMPLES, MAX_DEPTH).unwrap();
+    Ok(checkpoint::fingerprint(&key))
 }
 
-fn final_scene<R: Rng>(rng: &mut R) -> World {
-    let mut world = World(vec![]);
-    let ground = Box::new(Sphere::new(
-        Point3::new(0., -1000., 0.),
-        1000.,
-        Material::Lambertian {
-            albedo: Color::from_elem(0.5),
-        },
-    ));
-
-    world.add(ground);
-
-    for a in -11..11 {
-        for b in -11..11 {
-            let choose_mat = rng.gen::<Num>();
-            let center = Point3::new(
-                a as Num + 0.9 * rng.gen::<Num>(),
-                0.2,
-                b as Num + 0.9 * rng.gen::<Num>(),
-            );
-
-            if (center - Point3::new(4., 0.2, 0.)).length() > 0.9 {
-                match choose_mat {
-                    c if c < 0.8 => {
-                        let albedo = Color::random(rng) * Color::random(rng);
-                        world.add(Box::new(Sphere::new(
-                            center,
-                            0.2,
-                            Material::Lambertian { albedo },
-                        )));
-                    }
-                    c if c < 0.95 => {
-                        let albedo = Color::random_double(0.5..1., rng);
-                        let fuzz = rng.gen_range(0. ..0.5);
-                        world.add(Box::new(Sphere::new(
-                            center,
-                            0.2,
-                            Material::Metal { albedo, fuzz },
-                        )));
-                    }
-                    _ => {
-                        world.add(Box::new(Sphere::new(
-                            center,
-                            0.2,
-                            Material::Dielectric { ir: 1.5 },
-                        )));
-                    }
-                }
-            }
-        }
-    }
-    world.add(Box::new(Sphere::new(
-        Point3::new(0., 1., 0.),
-        1.,
-        Material::Dielectric { ir: 1.5 },
-    )));
-    world.add(Box::new(Sphere::new(
-        Point3::new(-4., 1., 0.),
-        1.,
-        Material::Lambertian {
-            albedo: Color::new(0.4, 0.2, 0.1),
-        },
-    )));
-    world.add(Box::new(Sphere::new(
-        Point3::new(4., 1., 0.),
-        1.,
-        Material::Metal {
-            albedo: Color::new(0.7, 0.6, 0.5),
-            fuzz: 0.0,
+// Set to stop rendering, by Ctrl-C or the time limit
+static STOP: AtomicBool = AtomicBool::new(false);
+
+// Redraws a one line progress bar on stderr
+fn draw_progress(pass: usize, passes: usize, start: Instant, progress: &Progress) {
+    const WIDTH: usize = 30;
+    let filled = (progress.fraction() * WIDTH as Num).round() as usize;
+    // Over all passes, assuming they take equally long
+    let elapsed = start.elapsed();
+    let eta = if passes > 1 {
+        let done = (pass - 1) as f64 + progress.fraction() as f64;
+        (done > 0.).then(|| elapsed.mul_f64((passes as f64 - done) / done))
+    } else {
+        progress.eta()
+    };
+    let eta = match eta {
+        Some(eta) => format_duration(eta),
+        None => "?".to_string(),
+    };
+    let mut stderr = std::io::stderr().lock();
+    let _ = write!(
+        stderr,
+        "\r{}[{}{}] {}/{} tiles, {:.2} Msamples/s, {} elapsed, ETA {} ",
+        if passes > 1 {
+            format!("pass {}/{} ", pass, passes)
+        } else {
+            String::new()
         },
-    )));
+        "#".repeat(filled),
+        "-".repeat(WIDTH - filled),
+        progress.tiles_done,
+        progress.tiles_total,
+        progress.samples_per_second() / 1e6,
+        format_duration(elapsed),
+        eta
+    );
+    let _ = stderr.flush();
+}
 
-    world
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
 }
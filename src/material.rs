@@ -1,37 +1,63 @@
 use crate::hittable::HitRecord;
+use crate::pdf::Pdf;
 use crate::ray::Ray;
+use crate::texture::Texture;
 use crate::vec3::{Color, Vector3};
 use crate::Num;
 use rand::Rng;
+use std::f32::consts::PI;
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub enum Material {
-    Lambertian { albedo: Color },
-    Metal { albedo: Color, fuzz: Num },
+    Lambertian { albedo: Texture },
+    Metal { albedo: Texture, fuzz: Num },
     Dielectric { ir: Num },
+    DiffuseLight { emit: Color },
+    // Phase function of participating media, scatters uniformly in every direction
+    Isotropic { albedo: Texture },
+}
+
+/// How a material scatters a ray that hit it, see [`Material::scatter`].
+pub enum Scatter {
+    // Into a single direction, like a mirror or glass
+    Specular {
+        attenuation: Color,
+        ray: Ray,
+    },
+    // Into directions spread by `pdf`, weighted by `Material::scattering_pdf`
+    Diffuse {
+        attenuation: Color,
+        pdf: Pdf<'static>,
+    },
 }
 
 impl Material {
-    pub fn scatter<R: Rng>(&self, ray: Ray, rec: HitRecord, rng: &mut R) -> Option<(Color, Ray)> {
+    pub fn emitted(&self) -> Color {
         match *self {
-            Material::Lambertian { albedo } => {
-                let mut scatter_dir = rec.normal + Vector3::random_unit_vector(rng);
-                if scatter_dir.near_zero() {
-                    scatter_dir = rec.normal;
-                }
+            Material::DiffuseLight { emit } => emit,
+            _ => Color::zeros(),
+        }
+    }
 
-                let scattered = Ray::from(rec.p, scatter_dir);
-                Some((albedo, scattered))
-            }
-            Material::Metal { albedo, fuzz } => {
+    pub fn scatter<R: Rng>(&self, ray: Ray, rec: HitRecord, rng: &mut R) -> Option<Scatter> {
+        match *self {
+            Material::Lambertian { ref albedo } => Some(Scatter::Diffuse {
+                attenuation: albedo.value(rec.u, rec.v, rec.p),
+                pdf: Pdf::Cosine { w: rec.normal },
+            }),
+            Material::Metal { ref albedo, fuzz } => {
                 let reflected = ray.direction.normalize().reflect(rec.normal);
                 let scattered = Ray::from(
                     rec.p,
                     reflected + fuzz * Vector3::random_in_unit_sphere(rng),
+                    ray.time,
                 );
 
                 if scattered.direction.dot(rec.normal) > 0. {
-                    Some((albedo, scattered))
+                    Some(Scatter::Specular {
+                        attenuation: albedo.value(rec.u, rec.v, rec.p),
+                        ray: scattered,
+                    })
                 } else {
                     None
                 }
@@ -58,9 +84,28 @@ impl Material {
                         unit_direction.refract(rec.normal, refraction_ratio)
                     };
 
-                let scattered = Ray::from(rec.p, direction);
-                Some((attenuation, scattered))
+                Some(Scatter::Specular {
+                    attenuation,
+                    ray: Ray::from(rec.p, direction, ray.time),
+                })
+            }
+            Material::DiffuseLight { .. } => None,
+            Material::Isotropic { ref albedo } => Some(Scatter::Diffuse {
+                attenuation: albedo.value(rec.u, rec.v, rec.p),
+                pdf: Pdf::UniformSphere,
+            }),
+        }
+    }
+
+    /// Density, per unit solid angle, of a diffuse material at `rec` scattering into `scattered`.
+    pub fn scattering_pdf(&self, rec: &HitRecord, scattered: Ray) -> Num {
+        match *self {
+            Material::Lambertian { .. } => {
+                let cosine = rec.normal.dot(scattered.direction.normalize());
+                cosine.max(0.) / PI
             }
+            Material::Isotropic { .. } => 1. / (4. * PI),
+            _ => 0.,
         }
     }
 }
@@ -0,0 +1,71 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::texture::Texture;
+use crate::vec3::Vector3;
+use crate::Num;
+use rand::{Rng, RngCore};
+use std::ops::Range;
+
+/// Participating medium of constant density filling a convex `boundary`, such as smoke or fog.
+pub struct ConstantMedium {
+    boundary: Box<dyn Hittable>,
+    neg_inv_density: Num,
+    phase_function: Material,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Box<dyn Hittable>, density: Num, albedo: Texture) -> Self {
+        Self {
+            boundary,
+            neg_inv_density: -1. / density,
+            phase_function: Material::Isotropic { albedo },
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, ray: Ray, range: Range<Num>, rng: &mut dyn RngCore) -> Option<HitRecord<'_>> {
+        // Where the ray enters and leaves the boundary, even if it starts inside
+        let enter = self
+            .boundary
+            .hit(ray, Num::NEG_INFINITY..Num::INFINITY, rng)?;
+        let exit = self
+            .boundary
+            .hit(ray, enter.t + 0.0001..Num::INFINITY, rng)?;
+
+        let t_enter = enter.t.max(range.start).max(0.);
+        let t_exit = exit.t.min(range.end);
+        if t_enter >= t_exit {
+            return None;
+        }
+
+        let ray_length = ray.direction.length();
+        let distance_inside = (t_exit - t_enter) * ray_length;
+        // Exponentially distributed free path, drawn from the sample's own random numbers
+        let hit_distance = self.neg_inv_density * (1. - rng.gen::<Num>()).ln();
+        if hit_distance > distance_inside {
+            return None;
+        }
+
+        let t = t_enter + hit_distance / ray_length;
+        let p = ray.at(t);
+
+        // The normal and uv are arbitrary, the isotropic phase function ignores them
+        let mut rec = HitRecord::new(
+            ray,
+            Vector3::new(1., 0., 0.),
+            p,
+            t,
+            (0., 0.),
+            &self.phase_function,
+        );
+        rec.front_face = true;
+        Some(rec)
+    }
+
+    fn bounding_box(&self, time: Range<Num>) -> Option<Aabb> {
+        self.boundary.bounding_box(time)
+    }
+}
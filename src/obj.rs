@@ -0,0 +1,277 @@
+use crate::material::Material;
+use crate::texture::{ImageTexture, Texture};
+use crate::triangle::Triangle;
+use crate::vec3::{Color, Point3, Vector3};
+use crate::world::World;
+use crate::Num;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Reads a Wavefront .obj file into a world of triangles, wrap it in a [`crate::bvh::Bvh`] to
+/// render it efficiently.
+///
+/// Materials come from the .mtl libraries named by `mtllib`, faces before any `usemtl` or with an
+/// unknown material use `default_material`. Polygons are split into triangle fans.
+pub fn load<P: AsRef<Path>>(path: P, default_material: Material) -> io::Result<World> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut positions: Vec<Point3> = vec![];
+    let mut normals: Vec<Vector3> = vec![];
+    let mut uvs: Vec<(Num, Num)> = vec![];
+    let mut materials: HashMap<String, Arc<Material>> = HashMap::new();
+    let default_material = Arc::new(default_material);
+    let mut current = default_material.clone();
+    let mut world = World(vec![]);
+
+    for (i, line) in source.lines().enumerate() {
+        let error = |msg: &str| parse_error(path, i + 1, msg);
+        let mut tokens = line
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => positions.push(parse_vector(&args).ok_or_else(|| error("invalid vertex"))?),
+            "vn" => normals.push(parse_vector(&args).ok_or_else(|| error("invalid normal"))?),
+            "vt" => {
+                let u = args.first().and_then(|u| u.parse().ok());
+                let v = args.get(1).map_or(Some(0.), |v| v.parse().ok());
+                match (u, v) {
+                    (Some(u), Some(v)) => uvs.push((u, v)),
+                    _ => return Err(error("invalid texture coordinate")),
+                }
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(error("face needs at least three vertices"));
+                }
+                let corners = args
+                    .iter()
+                    .map(|corner| {
+                        parse_corner(corner, positions.len(), uvs.len(), normals.len())
+                            .ok_or_else(|| error(&format!("invalid face vertex '{}'", corner)))
+                    })
+                    .collect::<io::Result<Vec<_>>>()?;
+
+                for k in 1..corners.len() - 1 {
+                    let fan = [corners[0], corners[k], corners[k + 1]];
+                    let face_normals = fan
+                        .iter()
+                        .map(|c| c.normal.map(|n| normals[n]))
+                        .collect::<Option<Vec<_>>>();
+                    let face_uvs = fan
+                        .iter()
+                        .map(|c| c.uv.map(|t| uvs[t]))
+                        .collect::<Option<Vec<_>>>();
+
+                    world.add(Box::new(Triangle::new(
+                        fan.map(|c| positions[c.position]),
+                        face_normals.map(|n| [n[0], n[1], n[2]]),
+                        face_uvs.map(|t| [t[0], t[1], t[2]]),
+                        current.clone(),
+                    )));
+                }
+            }
+            "mtllib" => {
+                for library in args {
+                    materials.extend(load_mtl(&dir.join(library))?);
+                }
+            }
+            "usemtl" => {
+                current = args
+                    .first()
+                    .and_then(|name| materials.get(*name))
+                    .unwrap_or(&default_material)
+                    .clone();
+            }
+            // Groups, objects and smoothing groups don't affect rendering
+            _ => {}
+        }
+    }
+
+    Ok(world)
+}
+
+// Reads the materials of a .mtl library, keyed by name.
+//
+// Emissive materials (`Ke`) become lights, transparent ones (`d` < 1 or a refracting `illum`)
+// dielectrics using `Ni`, reflective ones (`illum` 3) metals whose fuzz comes from the `Ns`
+// exponent, and everything else Lambertian with `Kd` or the `map_Kd` image, which can be a PNG
+// or PPM file.
+fn load_mtl(path: &Path) -> io::Result<HashMap<String, Arc<Material>>> {
+    let source = fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlParams)> = None;
+
+    for (i, line) in source.lines().enumerate() {
+        let error = |msg: &str| parse_error(path, i + 1, msg);
+        let mut tokens = line
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            let name = args.first().ok_or_else(|| error("material needs a name"))?;
+            if let Some((name, params)) = current.take() {
+                materials.insert(name, Arc::new(params.into_material()));
+            }
+            current = Some((name.to_string(), MtlParams::default()));
+            continue;
+        }
+
+        let Some((_, params)) = current.as_mut() else {
+            continue;
+        };
+        let number = || {
+            args.first()
+                .and_then(|n| n.parse::<Num>().ok())
+                .ok_or_else(|| error(&format!("invalid value for {}", keyword)))
+        };
+        let color = || parse_vector(&args).ok_or_else(|| error(&format!("invalid {}", keyword)));
+
+        match keyword {
+            "Kd" => params.diffuse = color()?,
+            "Ks" => params.specular = color()?,
+            "Ke" => params.emission = color()?,
+            "Ns" => params.shininess = number()?,
+            "Ni" => params.ior = number()?,
+            "d" => params.dissolve = number()?,
+            "Tr" => params.dissolve = 1. - number()?,
+            "illum" => params.illum = number()? as u32,
+            "map_Kd" => {
+                // Options such as `-s` precede the file name, which is always last
+                let file = args.last().ok_or_else(|| error("map_Kd needs a file"))?;
+                let image = ImageTexture::load(dir.join(file))
+                    .map_err(|e| error(&format!("cannot load {}: {}", file, e)))?;
+                params.diffuse_map = Some(Texture::Image(Arc::new(image)));
+            }
+            _ => {}
+        }
+    }
+    if let Some((name, params)) = current {
+        materials.insert(name, Arc::new(params.into_material()));
+    }
+
+    Ok(materials)
+}
+
+struct MtlParams {
+    diffuse: Color,
+    diffuse_map: Option<Texture>,
+    specular: Color,
+    emission: Color,
+    shininess: Num,
+    ior: Num,
+    dissolve: Num,
+    illum: u32,
+}
+
+impl Default for MtlParams {
+    fn default() -> Self {
+        Self {
+            diffuse: Color::from_elem(0.8),
+            diffuse_map: None,
+            specular: Color::zeros(),
+            emission: Color::zeros(),
+            shininess: 0.,
+            ior: 1.5,
+            dissolve: 1.,
+            illum: 2,
+        }
+    }
+}
+
+impl MtlParams {
+    fn into_material(self) -> Material {
+        if !self.emission.near_zero() {
+            return Material::DiffuseLight {
+                emit: self.emission,
+            };
+        }
+        if self.dissolve < 1. || matches!(self.illum, 4 | 6 | 7 | 9) {
+            return Material::Dielectric { ir: self.ior };
+        }
+        match self.illum {
+            3 => Material::Metal {
+                albedo: self.specular.into(),
+                // Blinn-Phong exponent to an approximate roughness
+                fuzz: (2. / (self.shininess + 2.)).sqrt(),
+            },
+            _ => Material::Lambertian {
+                albedo: self.diffuse_map.unwrap_or_else(|| self.diffuse.into()),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+// `v`, `v/vt`, `v//vn` or `v/vt/vn`, with 1-based or negative (relative to the end) indices
+fn parse_corner(corner: &str, positions: usize, uvs: usize, normals: usize) -> Option<Corner> {
+    let resolve = |index: &str, len: usize| -> Option<usize> {
+        let index: i64 = index.parse().ok()?;
+        let resolved = if index < 0 {
+            len as i64 + index
+        } else {
+            index - 1
+        };
+        (0..len as i64)
+            .contains(&resolved)
+            .then_some(resolved as usize)
+    };
+    let optional = |index: Option<&str>, len: usize| match index {
+        None | Some("") => Some(None),
+        Some(index) => resolve(index, len).map(Some),
+    };
+
+    let mut parts = corner.split('/');
+    let position = resolve(parts.next()?, positions)?;
+    let uv = optional(parts.next(), uvs)?;
+    let normal = optional(parts.next(), normals)?;
+
+    Some(Corner {
+        position,
+        uv,
+        normal,
+    })
+}
+
+fn parse_vector(args: &[&str]) -> Option<Vector3> {
+    match args {
+        [x, y, z, ..] => Some(Vector3::new(
+            x.parse().ok()?,
+            y.parse().ok()?,
+            z.parse().ok()?,
+        )),
+        _ => None,
+    }
+}
+
+fn parse_error(path: &Path, line: usize, msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}:{}: {}", path.display(), line, msg),
+    )
+}
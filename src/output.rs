@@ -0,0 +1,278 @@
+use crate::film::Film;
+use crate::hdr;
+use crate::hdr::ExrPrecision;
+use crate::tonemap::DisplayTransform;
+use crate::vec3::Color;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Image file formats the renderer can write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    // Plain text P3
+    PpmAscii,
+    // Binary P6
+    Ppm,
+    Png,
+    // Run-length encoded truecolor
+    Tga,
+    // Uncompressed 24 bits per pixel
+    Bmp,
+    // Radiance RGBE and OpenEXR store the linear radiance, without any display transform
+    Hdr,
+    Exr,
+    ExrFloat,
+}
+
+impl ImageFormat {
+    pub const ALL: [ImageFormat; 8] = [
+        ImageFormat::PpmAscii,
+        ImageFormat::Ppm,
+        ImageFormat::Png,
+        ImageFormat::Tga,
+        ImageFormat::Bmp,
+        ImageFormat::Hdr,
+        ImageFormat::Exr,
+        ImageFormat::ExrFloat,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ImageFormat::PpmAscii => "ppm-ascii",
+            ImageFormat::Ppm => "ppm",
+            ImageFormat::Png => "png",
+            ImageFormat::Tga => "tga",
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Hdr => "hdr",
+            ImageFormat::Exr => "exr",
+            ImageFormat::ExrFloat => "exr-float",
+        }
+    }
+
+    /// Whether the format keeps linear, unclamped radiance.
+    pub fn is_hdr(self) -> bool {
+        matches!(
+            self,
+            ImageFormat::Hdr | ImageFormat::Exr | ImageFormat::ExrFloat
+        )
+    }
+
+    /// Guesses the format from the file extension, `.ppm` files are written as binary P6 and
+    /// `.exr` files with half floats.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(ImageFormat::Ppm),
+            "png" => Some(ImageFormat::Png),
+            "tga" => Some(ImageFormat::Tga),
+            "bmp" => Some(ImageFormat::Bmp),
+            "hdr" => Some(ImageFormat::Hdr),
+            "exr" => Some(ImageFormat::Exr),
+            _ => None,
+        }
+    }
+
+    /// Encodes the average pixel radiance, row-major from the top row. Only low dynamic range
+    /// formats go through the display `transform`.
+    pub fn write<W: Write>(
+        self,
+        writer: W,
+        width: usize,
+        height: usize,
+        radiance: &[Color],
+        transform: &DisplayTransform,
+    ) -> io::Result<()> {
+        assert_eq!(radiance.len(), width * height);
+        match self {
+            ImageFormat::Hdr => return hdr::write_rgbe(writer, width, height, radiance),
+            ImageFormat::Exr => {
+                return hdr::write_exr(writer, width, height, radiance, ExrPrecision::Half)
+            }
+            ImageFormat::ExrFloat => {
+                return hdr::write_exr(writer, width, height, radiance, ExrPrecision::Float)
+            }
+            _ => {}
+        }
+
+        let rgb = to_rgb8(radiance, transform);
+        match self {
+            ImageFormat::PpmAscii => write_ppm_ascii(writer, width, height, &rgb),
+            ImageFormat::Ppm => write_ppm(writer, width, height, &rgb),
+            ImageFormat::Png => write_png(writer, width, height, &rgb),
+            ImageFormat::Tga => write_tga(writer, width, height, &rgb),
+            _ => write_bmp(writer, width, height, &rgb),
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|f| f.name()).collect();
+                format!(
+                    "unknown image format `{}`, expected one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Converts average pixel radiance to 8 bit RGB.
+pub fn to_rgb8(radiance: &[Color], transform: &DisplayTransform) -> Vec<u8> {
+    radiance.iter().flat_map(|&c| transform.rgb8(c)).collect()
+}
+
+/// Writes `film` to `path`, in `format` or else the one matching the extension.
+pub fn save(
+    path: &Path,
+    format: Option<ImageFormat>,
+    film: &Film,
+    transform: &DisplayTransform,
+) -> io::Result<()> {
+    let format = format
+        .or_else(|| ImageFormat::from_path(path))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot tell the image format of {}", path.display()),
+            )
+        })?;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    format.write(
+        &mut writer,
+        film.width(),
+        film.height(),
+        &film.pixels(),
+        transform,
+    )?;
+    writer.flush()
+}
+
+fn write_ppm_ascii<W: Write>(
+    mut writer: W,
+    width: usize,
+    height: usize,
+    rgb: &[u8],
+) -> io::Result<()> {
+    writeln!(writer, "P3\n{} {}\n255", width, height)?;
+    for p in rgb.chunks_exact(3) {
+        writeln!(writer, "{} {} {}", p[0], p[1], p[2])?;
+    }
+    Ok(())
+}
+
+fn write_ppm<W: Write>(mut writer: W, width: usize, height: usize, rgb: &[u8]) -> io::Result<()> {
+    write!(writer, "P6\n{} {}\n255\n", width, height)?;
+    writer.write_all(rgb)
+}
+
+fn write_png<W: Write>(writer: W, width: usize, height: usize, rgb: &[u8]) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(png::Compression::Best);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgb)?;
+    Ok(writer.finish()?)
+}
+
+fn write_tga<W: Write>(mut writer: W, width: usize, height: usize, rgb: &[u8]) -> io::Result<()> {
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "image too large for TGA");
+    let w = u16::try_from(width).map_err(|_| too_large())?;
+    let h = u16::try_from(height).map_err(|_| too_large())?;
+    let mut header = [0u8; 18];
+    header[2] = 10; // run-length encoded truecolor
+    header[12..14].copy_from_slice(&w.to_le_bytes());
+    header[14..16].copy_from_slice(&h.to_le_bytes());
+    header[16] = 24;
+    header[17] = 0x20; // top-left origin
+    writer.write_all(&header)?;
+
+    // Packets never cross rows, and hold at most 128 pixels
+    let mut packets = vec![];
+    for row in rgb.chunks_exact(width * 3) {
+        let pixels: Vec<[u8; 3]> = row.chunks_exact(3).map(|p| [p[2], p[1], p[0]]).collect();
+        let mut i = 0;
+        while i < pixels.len() {
+            let run = pixels[i..]
+                .iter()
+                .take(128)
+                .take_while(|&&p| p == pixels[i])
+                .count();
+            if run > 1 {
+                packets.push(0x80 | (run - 1) as u8);
+                packets.extend_from_slice(&pixels[i]);
+                i += run;
+            } else {
+                // Raw packet up to the start of the next run
+                let mut len = 1;
+                while i + len < pixels.len()
+                    && len < 128
+                    && (i + len + 1 >= pixels.len() || pixels[i + len] != pixels[i + len + 1])
+                {
+                    len += 1;
+                }
+                packets.push((len - 1) as u8);
+                for p in &pixels[i..i + len] {
+                    packets.extend_from_slice(p);
+                }
+                i += len;
+            }
+        }
+    }
+    writer.write_all(&packets)
+}
+
+fn write_bmp<W: Write>(mut writer: W, width: usize, height: usize, rgb: &[u8]) -> io::Result<()> {
+    // Rows are padded to four bytes and stored bottom-up
+    let row_size = (width * 3 + 3) & !3;
+    // Sizes are 32 bits, small enough that the dimensions then fit too
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "image too large for BMP");
+    let data_size = row_size
+        .checked_mul(height)
+        .and_then(|size| u32::try_from(size).ok())
+        .ok_or_else(too_large)?;
+    let file_size = data_size.checked_add(14 + 40).ok_or_else(too_large)?;
+
+    writer.write_all(b"BM")?;
+    writer.write_all(&file_size.to_le_bytes())?;
+    writer.write_all(&[0; 4])?;
+    writer.write_all(&(14u32 + 40).to_le_bytes())?;
+
+    writer.write_all(&40u32.to_le_bytes())?;
+    writer.write_all(&(width as i32).to_le_bytes())?;
+    writer.write_all(&(height as i32).to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&24u16.to_le_bytes())?;
+    writer.write_all(&0u32.to_le_bytes())?; // BI_RGB
+    writer.write_all(&data_size.to_le_bytes())?;
+    writer.write_all(&2835i32.to_le_bytes())?; // 72 DPI
+    writer.write_all(&2835i32.to_le_bytes())?;
+    writer.write_all(&[0; 8])?;
+
+    let mut row_bytes = vec![0u8; row_size];
+    for row in rgb.chunks_exact(width * 3).rev() {
+        for (dst, src) in row_bytes.chunks_exact_mut(3).zip(row.chunks_exact(3)) {
+            dst.copy_from_slice(&[src[2], src[1], src[0]]);
+        }
+        writer.write_all(&row_bytes)?;
+    }
+    Ok(())
+}
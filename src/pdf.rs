@@ -0,0 +1,87 @@
+use crate::light::Light;
+use crate::vec3::{Point3, Vector3};
+use crate::Num;
+use rand::{Rng, RngCore};
+use std::f32::consts::PI;
+
+/// Probability density over directions, with a way to draw directions following it.
+pub enum Pdf<'a> {
+    // Proportional to the cosine to the normal `w`, as scattered by a Lambertian surface
+    Cosine {
+        w: Vector3,
+    },
+    UniformSphere,
+    // Directions from `origin` towards `light`, see `Light::random`
+    Light {
+        light: &'a dyn Light,
+        origin: Point3,
+    },
+    // Equal parts of both
+    Mixture(&'a Pdf<'a>, &'a Pdf<'a>),
+}
+
+impl Pdf<'_> {
+    /// Density of `direction`, per unit solid angle.
+    pub fn value(&self, direction: Vector3) -> Num {
+        match *self {
+            Pdf::Cosine { w } => {
+                let cosine = direction.normalize().dot(w);
+                cosine.max(0.) / PI
+            }
+            Pdf::UniformSphere => 1. / (4. * PI),
+            Pdf::Light { light, origin } => light.pdf_value(origin, direction),
+            Pdf::Mixture(a, b) => 0.5 * a.value(direction) + 0.5 * b.value(direction),
+        }
+    }
+
+    /// Random direction distributed by this density, not necessarily of unit length.
+    pub fn generate<R: RngCore>(&self, rng: &mut R) -> Vector3 {
+        match *self {
+            Pdf::Cosine { w } => Onb::new(w).local(random_cosine_direction(rng)),
+            Pdf::UniformSphere => Vector3::random_unit_vector(rng),
+            Pdf::Light { light, origin } => light.random(origin, rng),
+            Pdf::Mixture(a, b) => {
+                if rng.gen::<bool>() {
+                    a.generate(rng)
+                } else {
+                    b.generate(rng)
+                }
+            }
+        }
+    }
+}
+
+/// Orthonormal basis with `w` along a given direction.
+pub struct Onb {
+    pub u: Vector3,
+    pub v: Vector3,
+    pub w: Vector3,
+}
+
+impl Onb {
+    pub fn new(w: Vector3) -> Self {
+        let w = w.normalize();
+        let a = if w.x.abs() > 0.9 {
+            Vector3::new(0., 1., 0.)
+        } else {
+            Vector3::new(1., 0., 0.)
+        };
+        let v = w.cross(a).normalize();
+        let u = w.cross(v);
+        Self { u, v, w }
+    }
+
+    /// World space vector of the coordinates `a` in this basis.
+    pub fn local(&self, a: Vector3) -> Vector3 {
+        a.x * self.u + a.y * self.v + a.z * self.w
+    }
+}
+
+// Unit vector around +Z with a density proportional to its z
+fn random_cosine_direction<R: Rng>(rng: &mut R) -> Vector3 {
+    let r1 = rng.gen::<Num>();
+    let r2 = rng.gen::<Num>();
+    let phi = 2. * PI * r1;
+    let z = (1. - r2).sqrt();
+    Vector3::new(phi.cos() * r2.sqrt(), phi.sin() * r2.sqrt(), z)
+}
@@ -0,0 +1,117 @@
+use crate::vec3::{Point3, Vector3};
+use crate::Num;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// Gradient noise generator, see Ken Perlin's "Improving Noise".
+pub struct Perlin {
+    ranvec: Vec<Vector3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+/// How a [`Perlin`] noise texture turns noise into a color.
+#[derive(Clone, Copy)]
+pub enum NoisePattern {
+    Smooth,
+    Turbulence,
+    // Veins following the z axis, phase shifted by turbulence
+    Marble,
+    // Concentric rings around the y axis
+    Wood,
+}
+
+impl Perlin {
+    const POINT_COUNT: usize = 256;
+    const TURBULENCE_DEPTH: usize = 7;
+
+    pub fn new<R: Rng>(rng: &mut R) -> Self {
+        let ranvec = (0..Self::POINT_COUNT)
+            .map(|_| Vector3::random_double(-1. ..1., rng).normalize())
+            .collect();
+
+        Self {
+            ranvec,
+            perm_x: Self::generate_perm(rng),
+            perm_y: Self::generate_perm(rng),
+            perm_z: Self::generate_perm(rng),
+        }
+    }
+
+    fn generate_perm<R: Rng>(rng: &mut R) -> Vec<usize> {
+        let mut p: Vec<usize> = (0..Self::POINT_COUNT).collect();
+        p.shuffle(rng);
+        p
+    }
+
+    /// Noise in `[-1, 1]`, trilinearly interpolated between the gradients at the lattice corners.
+    pub fn noise(&self, p: Point3) -> Num {
+        let (u, v, w) = (p.x - p.x.floor(), p.y - p.y.floor(), p.z - p.z.floor());
+        let (i, j, k) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+
+        let mut c = [[[Vector3::zeros(); 2]; 2]; 2];
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, corner) in row.iter_mut().enumerate() {
+                    let hash = self.perm_x[((i + di as i64) & 255) as usize]
+                        ^ self.perm_y[((j + dj as i64) & 255) as usize]
+                        ^ self.perm_z[((k + dk as i64) & 255) as usize];
+                    *corner = self.ranvec[hash];
+                }
+            }
+        }
+
+        // Hermite cubic smoothing to hide the lattice
+        let (uu, vv, ww) = (
+            u * u * (3. - 2. * u),
+            v * v * (3. - 2. * v),
+            w * w * (3. - 2. * w),
+        );
+
+        let mut accum = 0.;
+        for (di, plane) in c.iter().enumerate() {
+            for (dj, row) in plane.iter().enumerate() {
+                for (dk, corner) in row.iter().enumerate() {
+                    let (fi, fj, fk) = (di as Num, dj as Num, dk as Num);
+                    let weight = Vector3::new(u - fi, v - fj, w - fk);
+                    accum += (fi * uu + (1. - fi) * (1. - uu))
+                        * (fj * vv + (1. - fj) * (1. - vv))
+                        * (fk * ww + (1. - fk) * (1. - ww))
+                        * corner.dot(weight);
+                }
+            }
+        }
+        accum
+    }
+
+    /// Sum of `depth` octaves of noise, each at double the frequency and half the weight.
+    pub fn turbulence(&self, p: Point3, depth: usize) -> Num {
+        let mut accum = 0.;
+        let mut temp_p = p;
+        let mut weight = 1.;
+
+        for _ in 0..depth {
+            accum += weight * self.noise(temp_p);
+            weight *= 0.5;
+            temp_p *= 2.;
+        }
+        accum.abs()
+    }
+
+    /// Grey level in `[0, 1]` of `pattern` at `p`.
+    pub fn pattern(&self, pattern: NoisePattern, p: Point3) -> Num {
+        match pattern {
+            NoisePattern::Smooth => 0.5 * (1. + self.noise(p)),
+            NoisePattern::Turbulence => self.turbulence(p, Self::TURBULENCE_DEPTH).min(1.),
+            NoisePattern::Marble => {
+                0.5 * (1. + (p.z + 10. * self.turbulence(p, Self::TURBULENCE_DEPTH)).sin())
+            }
+            NoisePattern::Wood => {
+                let rings = 4. * (p.x * p.x + p.z * p.z).sqrt()
+                    + 2. * self.turbulence(p, Self::TURBULENCE_DEPTH);
+                rings - rings.floor()
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use rand::SeedableRng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+/// Random number generator used while rendering. A fixed algorithm rather than `SmallRng`, which
+/// differs between platforms and rand versions, so that a seed always gives the same image.
+pub type RenderRng = Xoshiro256PlusPlus;
+
+/// Independent stream for sample `sample` of the pixel with row-major index `pixel`, derived
+/// from the render `seed` so that the result doesn't depend on which thread takes the sample.
+pub fn sample_stream(seed: u64, pixel: u64, sample: u64) -> RenderRng {
+    RenderRng::seed_from_u64(hash(hash(seed ^ hash(pixel)) ^ sample))
+}
+
+/// Well mixed hash of `words`, e.g. a seed, a pixel and a dimension.
+pub fn hash_words(words: &[u64]) -> u64 {
+    words
+        .iter()
+        .fold(0x9e37_79b9_7f4a_7c15, |h, &w| hash(h ^ w))
+}
+
+// SplitMix64 finalizer
+fn hash(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+
+    #[test]
+    fn streams_are_pinned() {
+        // Xoshiro256++ seeded through SplitMix64, as computed by a separate implementation. If
+        // these change, so does every render
+        let mut rng = sample_stream(64206, 0, 0);
+        assert_eq!(rng.next_u64(), 10377897680627266401);
+        assert_eq!(rng.next_u64(), 3826152727821303070);
+        let mut rng = sample_stream(64206, 12, 3);
+        assert_eq!(rng.next_u64(), 1279426123491748576);
+        assert_eq!(rng.next_u64(), 12620809841143941371);
+    }
+
+    #[test]
+    fn streams_differ_per_pixel_and_sample() {
+        let first = |seed, pixel, sample| sample_stream(seed, pixel, sample).next_u64();
+        assert_ne!(first(1, 0, 0), first(2, 0, 0));
+        assert_ne!(first(1, 0, 0), first(1, 1, 0));
+        assert_ne!(first(1, 0, 0), first(1, 0, 1));
+        // Swapping the pixel and the sample index gives another stream
+        assert_ne!(first(1, 2, 3), first(1, 3, 2));
+    }
+}
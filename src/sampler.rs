@@ -0,0 +1,534 @@
+use crate::rng::{self, RenderRng};
+use crate::Num;
+use rand::{Rng, RngCore};
+use std::fmt;
+use std::str::FromStr;
+
+/// Largest `Num` below 1, where the samples are clamped to stay in `[0, 1)`.
+const ONE_MINUS_EPSILON: Num = 1. - Num::EPSILON / 2.;
+
+/// Source of the random numbers of a sample, handed out one dimension at a time.
+///
+/// The camera takes the first five dimensions, for the position within the pixel, on the lens and
+/// in time. The integrator takes the rest as it goes along the path, directly or through the
+/// [`RngCore`] implementation of `dyn Sampler` that the materials and lights draw from. Samplers
+/// that spread the samples of a pixel more evenly than independent random numbers do make an
+/// image converge faster, most of all in its first few dimensions.
+///
+/// Sample `index` of a pixel only depends on the seed, the pixel and the index, so renders stay
+/// the same whatever the thread count, tile order or passes.
+pub trait Sampler {
+    /// Starts sample `index` of the pixel with row-major index `pixel`, at its first dimension.
+    fn start_pixel_sample(&mut self, pixel: u64, index: u32);
+
+    /// Next dimension of the sample, in `[0, 1)`.
+    fn get_1d(&mut self) -> Num;
+
+    /// Next two dimensions of the sample, spread evenly together if the sampler can.
+    fn get_2d(&mut self) -> [Num; 2] {
+        [self.get_1d(), self.get_1d()]
+    }
+}
+
+/// Samplers selectable by name, see [`SamplerKind::build`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SamplerKind {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+}
+
+impl SamplerKind {
+    /// The sampler, for renders seeded with `seed` taking `samples` samples per pixel.
+    pub fn build(self, seed: u64, samples: usize) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(seed, samples)),
+            SamplerKind::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(seed)),
+        }
+    }
+}
+
+/// Independent uniform random numbers, from the stream of [`rng::sample_stream`].
+pub struct IndependentSampler {
+    seed: u64,
+    rng: RenderRng,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: rng::sample_stream(seed, 0, 0),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, pixel: u64, index: u32) {
+        self.rng = rng::sample_stream(self.seed, pixel, index as u64);
+    }
+
+    fn get_1d(&mut self) -> Num {
+        self.rng.gen()
+    }
+}
+
+/// Jittered stratification: every dimension, or pair of dimensions for [`Sampler::get_2d`], is
+/// split into as many strata as there are samples per pixel, and each sample falls at a random
+/// place in a stratum of its own. Which stratum goes to which sample is shuffled per pixel and
+/// dimension, so the dimensions don't line up with each other.
+///
+/// Pixels given more samples than the sampler was built for take independent random numbers for
+/// the extra ones.
+pub struct StratifiedSampler {
+    seed: u64,
+    samples: u32,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+    // Jitter within the strata
+    rng: RenderRng,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64, samples: usize) -> Self {
+        Self {
+            seed,
+            samples: samples.clamp(1, u32::MAX as usize) as u32,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+            rng: rng::sample_stream(seed, 0, 0),
+        }
+    }
+
+    // Stratum of the current sample among `count` of them in the current dimension
+    fn stratum(&mut self, count: u32) -> u32 {
+        let hash = rng::hash_words(&[self.seed, self.pixel, self.dimension]);
+        permutation_element(self.index, count, hash as u32)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, pixel: u64, index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+        self.rng = rng::sample_stream(self.seed, pixel, index as u64);
+    }
+
+    fn get_1d(&mut self) -> Num {
+        if self.index >= self.samples {
+            return self.rng.gen();
+        }
+        let stratum = self.stratum(self.samples);
+        self.dimension += 1;
+        let jitter: Num = self.rng.gen();
+        ((stratum as Num + jitter) / self.samples as Num).min(ONE_MINUS_EPSILON)
+    }
+
+    fn get_2d(&mut self) -> [Num; 2] {
+        if self.index >= self.samples {
+            return [self.rng.gen(), self.rng.gen()];
+        }
+        // The squarest grid with a cell for each sample, some left empty if the count doesn't
+        // factor
+        let columns = (self.samples as f64).sqrt().ceil() as u32;
+        let rows = self.samples.div_ceil(columns);
+        let cell = self.stratum(columns * rows);
+        self.dimension += 2;
+        let [jx, jy]: [Num; 2] = self.rng.gen();
+        [
+            (((cell % columns) as Num + jx) / columns as Num).min(ONE_MINUS_EPSILON),
+            (((cell / columns) as Num + jy) / rows as Num).min(ONE_MINUS_EPSILON),
+        ]
+    }
+}
+
+/// Halton sequence, dimension `d` being the radical inverse of the sample index in the `d`th
+/// prime base, with the digits scrambled per pixel and dimension.
+///
+/// Only the first [`HaltonSampler::DIMENSIONS`] dimensions follow the sequence, as larger bases
+/// take more samples than any pixel gets to cover a dimension evenly. Further dimensions take
+/// independent random numbers.
+pub struct HaltonSampler {
+    seed: u64,
+    pixel: u64,
+    index: u32,
+    dimension: usize,
+    // Dimensions past the primes
+    rng: RenderRng,
+}
+
+// The first `HaltonSampler::DIMENSIONS` primes, one base per dimension
+const PRIMES: [u64; HaltonSampler::DIMENSIONS] = primes();
+
+impl HaltonSampler {
+    pub const DIMENSIONS: usize = 64;
+
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+            rng: rng::sample_stream(seed, 0, 0),
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, pixel: u64, index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+        self.rng = rng::sample_stream(self.seed, pixel, index as u64);
+    }
+
+    fn get_1d(&mut self) -> Num {
+        let Some(&base) = PRIMES.get(self.dimension) else {
+            return self.rng.gen();
+        };
+        let hash = rng::hash_words(&[self.seed, self.pixel, self.dimension as u64]);
+        self.dimension += 1;
+        scrambled_radical_inverse(base, self.index as u64, hash)
+    }
+}
+
+/// Sobol sequence with Owen scrambling, padded: every pair of dimensions is a separate
+/// two-dimensional Sobol sequence, scrambled per pixel and pair, with the samples shuffled per
+/// pair so the pairs don't line up with each other.
+///
+/// The first `2^m` samples of a pixel cover each pair of dimensions as evenly as `2^m` points
+/// can, whatever `m`, so the sampler also suits renders stopped or extended at any point.
+pub struct SobolSampler {
+    seed: u64,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    // Hash seeding the scrambles of the current dimension
+    fn hash(&self) -> u64 {
+        rng::hash_words(&[self.seed, self.pixel, self.dimension])
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, pixel: u64, index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> Num {
+        let hash = self.hash();
+        self.dimension += 1;
+        let index = nested_uniform_scramble(self.index, hash as u32);
+        to_unit(nested_uniform_scramble(
+            index.reverse_bits(),
+            (hash >> 32) as u32,
+        ))
+    }
+
+    fn get_2d(&mut self) -> [Num; 2] {
+        let hash = self.hash();
+        let second = rng::hash_words(&[hash]);
+        self.dimension += 2;
+        let index = nested_uniform_scramble(self.index, hash as u32);
+        [
+            to_unit(nested_uniform_scramble(
+                index.reverse_bits(),
+                (hash >> 32) as u32,
+            )),
+            to_unit(nested_uniform_scramble(sobol_second(index), second as u32)),
+        ]
+    }
+}
+
+/// Lets the materials, lights and anything else taking an [`Rng`] draw from the sampler, each
+/// `u32` or `u64` being one dimension. Floats, booleans and ranges all come from a single one.
+impl RngCore for dyn Sampler + '_ {
+    fn next_u32(&mut self) -> u32 {
+        (self.get_1d() as f64 * 2f64.powi(32)) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        (self.get_1d() as f64 * 2f64.powi(64)) as u64
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// Fraction `bits / 2^32`, keeping the bits a `Num` can hold so it stays below 1
+fn to_unit(bits: u32) -> Num {
+    (bits >> 8) as Num / (1 << 24) as Num
+}
+
+// Second dimension of the Sobol sequence, whose generator matrix comes from the polynomial
+// `x + 1`, as a fraction of 2^32
+fn sobol_second(mut index: u32) -> u32 {
+    let mut v = 1 << 31;
+    let mut bits = 0;
+    while index != 0 {
+        if index & 1 != 0 {
+            bits ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    bits
+}
+
+// Owen scrambling of a fraction of 2^32, flipping each bit at random depending on the bits above
+// it, by Burley's "Practical Hash-based Owen Scrambling". Applied to a sample index instead, it
+// shuffles the indices within every aligned block of a power of two.
+fn nested_uniform_scramble(bits: u32, seed: u32) -> u32 {
+    laine_karras_permutation(bits.reverse_bits(), seed).reverse_bits()
+}
+
+// Hash in which each bit only depends on the bits below it
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x
+}
+
+// Radical inverse of `index` in `base`, mirroring its digits around the radix point, with each
+// digit shifted by a random amount depending on its position and the digits before it
+fn scrambled_radical_inverse(base: u64, mut index: u64, hash: u64) -> Num {
+    let inv_base = 1. / base as f64;
+    let mut inv_base_m = 1.;
+    let mut reversed = 0;
+    // The digits so far behind a leading 1, which tells apart prefixes of different lengths
+    let mut prefix = 1;
+    // The zeros past the index's own digits are scrambled the same way, down to the precision of
+    // a `Num`, so that indices with fewer digits keep to their own strata
+    while index > 0 || inv_base_m > 1. / (1 << 24) as f64 {
+        let shift = rng::hash_words(&[hash ^ prefix]) % base;
+        let digit = (index % base + shift) % base;
+        index /= base;
+        reversed = reversed * base + digit;
+        prefix = prefix * base + digit;
+        inv_base_m *= inv_base;
+    }
+    // Random offset within the interval the digits leave, for the digits too fine to scramble
+    let tail = (rng::hash_words(&[hash ^ prefix]) >> 11) as f64 / (1u64 << 53) as f64;
+    (((reversed as f64 + tail) * inv_base_m) as Num).min(ONE_MINUS_EPSILON)
+}
+
+// Element `i` of a random permutation of `0..n` picked by `seed`, by Kensler's "Correlated
+// Multi-Jittered Sampling", walking the cycle of a hash on the next power of two until it lands
+// back within `0..n`
+fn permutation_element(mut i: u32, n: u32, seed: u32) -> u32 {
+    let mut w = n.wrapping_sub(1);
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            return i.wrapping_add(seed) % n;
+        }
+    }
+}
+
+const fn primes() -> [u64; HaltonSampler::DIMENSIONS] {
+    let mut primes = [0; HaltonSampler::DIMENSIONS];
+    let mut count = 0;
+    let mut candidate = 2;
+    while count < primes.len() {
+        let mut divisor = 2;
+        while divisor * divisor <= candidate && candidate % divisor != 0 {
+            divisor += 1;
+        }
+        if divisor * divisor > candidate {
+            primes[count] = candidate;
+            count += 1;
+        }
+        candidate += 1;
+    }
+    primes
+}
+
+impl fmt::Display for SamplerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SamplerKind::Independent => "independent",
+            SamplerKind::Stratified => "stratified",
+            SamplerKind::Halton => "halton",
+            SamplerKind::Sobol => "sobol",
+        })
+    }
+}
+
+impl FromStr for SamplerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "independent" => Ok(SamplerKind::Independent),
+            "stratified" => Ok(SamplerKind::Stratified),
+            "halton" => Ok(SamplerKind::Halton),
+            "sobol" => Ok(SamplerKind::Sobol),
+            _ => Err(format!(
+                "unknown sampler `{}`, expected independent, stratified, halton or sobol",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [SamplerKind; 4] = [
+        SamplerKind::Independent,
+        SamplerKind::Stratified,
+        SamplerKind::Halton,
+        SamplerKind::Sobol,
+    ];
+
+    // The first `count` samples of a pixel, each as `dimensions` values drawn one at a time
+    fn samples(sampler: &mut dyn Sampler, count: u32, dimensions: usize) -> Vec<Vec<Num>> {
+        (0..count)
+            .map(|index| {
+                sampler.start_pixel_sample(5, index);
+                (0..dimensions).map(|_| sampler.get_1d()).collect()
+            })
+            .collect()
+    }
+
+    // Whether exactly one point falls in each cell of a `columns` by `rows` grid
+    fn stratified(points: &[[Num; 2]], columns: usize, rows: usize) -> bool {
+        let mut cells = vec![0; columns * rows];
+        for [x, y] in points {
+            cells[(y * rows as Num) as usize * columns + (x * columns as Num) as usize] += 1;
+        }
+        cells.iter().all(|&n| n == 1)
+    }
+
+    #[test]
+    fn samples_stay_in_the_unit_interval() {
+        for kind in KINDS {
+            let mut sampler = kind.build(1, 16);
+            for sample in samples(sampler.as_mut(), 64, 100) {
+                assert!(sample.iter().all(|v| (0. ..1.).contains(v)), "{}", kind);
+            }
+        }
+    }
+
+    #[test]
+    fn samples_only_depend_on_pixel_and_index() {
+        for kind in KINDS {
+            let mut sampler = kind.build(1, 8);
+            let forward = samples(sampler.as_mut(), 8, 10);
+            let mut backward: Vec<_> = (0..8)
+                .rev()
+                .map(|index| {
+                    sampler.start_pixel_sample(5, index);
+                    (0..10).map(|_| sampler.get_1d()).collect::<Vec<_>>()
+                })
+                .collect();
+            backward.reverse();
+            assert_eq!(forward, backward, "{}", kind);
+        }
+    }
+
+    #[test]
+    fn stratified_sampler_stratifies() {
+        for (count, columns, rows) in [(16, 4, 4), (6, 3, 2)] {
+            let mut sampler = StratifiedSampler::new(1, count);
+            let mut lines = vec![];
+            let mut grid = vec![];
+            for index in 0..count as u32 {
+                sampler.start_pixel_sample(5, index);
+                lines.push([sampler.get_1d(), 0.]);
+                grid.push(sampler.get_2d());
+            }
+            assert!(stratified(&lines, count, 1));
+            assert!(stratified(&grid, columns, rows));
+        }
+    }
+
+    #[test]
+    fn halton_sampler_stratifies() {
+        let mut sampler = HaltonSampler::new(1);
+        // Base 2 and base 3, on their own and together
+        let points: Vec<[Num; 2]> = samples(&mut sampler, 36, 2)
+            .iter()
+            .map(|s| [s[0], s[1]])
+            .collect();
+        assert!(stratified(&points[..16], 16, 1));
+        assert!(stratified(&points[..9], 1, 9));
+        assert!(stratified(&points[..6], 2, 3));
+        assert!(stratified(&points, 4, 9));
+    }
+
+    #[test]
+    fn sobol_sampler_stratifies() {
+        let mut sampler = SobolSampler::new(1);
+        for count in [4, 16, 64] {
+            let mut lines = vec![];
+            let mut grid = vec![];
+            for index in 0..count as u32 {
+                sampler.start_pixel_sample(5, index);
+                lines.push([sampler.get_1d(), 0.]);
+                grid.push(sampler.get_2d());
+            }
+            assert!(stratified(&lines, count, 1));
+            // Every elementary interval of `count` cells
+            let mut columns = 1;
+            while columns <= count {
+                assert!(stratified(&grid, columns, count / columns), "{}", columns);
+                columns *= 2;
+            }
+        }
+    }
+}
@@ -0,0 +1,613 @@
+use crate::background::Background;
+use crate::bvh::Bvh;
+use crate::camera::{Camera, Lens};
+use crate::hittable::Hittable;
+use crate::image::Image;
+use crate::light::{Light, Lights};
+use crate::material::Material;
+use crate::medium::ConstantMedium;
+use crate::obj;
+use crate::perlin::{NoisePattern, Perlin};
+use crate::shapes::{Cuboid, MovingSphere, Sphere, XyRect, XzRect, YzRect};
+use crate::texture::{ImageTexture, Texture};
+use crate::transform::{Rotate, Scale, Translate};
+use crate::vec3::Vector3;
+use crate::world::World;
+use crate::Num;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Everything needed to render a scene loaded from a file.
+pub struct Scene {
+    pub camera: Camera,
+    pub image: Image,
+    pub world: World,
+    // Copies of the emitters in `world` that can be sampled, for aiming rays at them
+    pub lights: Lights,
+    pub background: Background,
+    pub samples: usize,
+    pub max_depth: usize,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(PathBuf, io::Error),
+    // Malformed TOML or fields of the wrong type, the message carries the line and column
+    Parse(PathBuf, toml::de::Error),
+    // Well formed but meaningless, e.g. an unknown material name
+    Invalid {
+        path: PathBuf,
+        field: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            SceneError::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+            SceneError::Invalid {
+                path,
+                field,
+                message,
+            } => write!(f, "{}: {}: {}", path.display(), field, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl Scene {
+    /// Reads a TOML scene description, see `scenes/` for examples.
+    ///
+    /// Relative paths to textures and meshes are resolved against the scene file's directory.
+    /// Top-level spheres and rectangles with a `diffuse_light` material are also sampled as lights.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|e| SceneError::Io(path.into(), e))?;
+        let desc: SceneDesc =
+            toml::from_str(&source).map_err(|e| SceneError::Parse(path.into(), e))?;
+
+        let mut builder = Builder {
+            path,
+            dir: path.parent().unwrap_or_else(|| Path::new("")),
+            perlin: Arc::new(Perlin::new(&mut StdRng::seed_from_u64(desc.seed))),
+            exposure: 0. ..1.,
+            materials: BTreeMap::new(),
+        };
+        builder.exposure = builder.time_range(desc.camera.exposure, "camera.exposure")?;
+        if !(desc.camera.vfov > 0. && desc.camera.vfov < 180.) {
+            return Err(builder.invalid("camera.vfov", "must be between 0 and 180 degrees".into()));
+        }
+        for (name, mat) in &desc.materials {
+            let mat = builder.material(mat, &format!("materials.{}", name))?;
+            builder.materials.insert(name.clone(), mat);
+        }
+
+        let image = builder.image(&desc.image)?;
+        let camera = desc.camera.build(image.aspect_ratio);
+        let mut world = World(vec![]);
+        let mut lights = Lights(vec![]);
+        for (i, object) in desc.objects.iter().enumerate() {
+            let field = format!("objects[{}]", i);
+            world.add(builder.object(object, &field)?);
+            if let Some(light) = builder.light(object, &field)? {
+                lights.add(light);
+            }
+        }
+
+        Ok(Self {
+            camera,
+            image,
+            world,
+            lights,
+            background: desc.background.build(),
+            samples: desc.image.samples,
+            max_depth: desc.image.max_depth,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDesc {
+    image: ImageDesc,
+    camera: CameraDesc,
+    #[serde(default)]
+    background: BackgroundDesc,
+    // Seeds the Perlin noise of `noise` textures
+    #[serde(default)]
+    seed: u64,
+    #[serde(default)]
+    materials: BTreeMap<String, MaterialDesc>,
+    #[serde(default)]
+    objects: Vec<ObjectDesc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ImageDesc {
+    width: usize,
+    // Either the height or the aspect ratio, defaulting to `Camera::ASPECT_RATIO`
+    height: Option<usize>,
+    aspect_ratio: Option<Num>,
+    #[serde(default = "default_samples")]
+    samples: usize,
+    #[serde(default = "default_max_depth")]
+    max_depth: usize,
+}
+
+fn default_samples() -> usize {
+    100
+}
+
+fn default_max_depth() -> usize {
+    50
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    look_from: [Num; 3],
+    look_at: [Num; 3],
+    #[serde(default = "default_vup")]
+    vup: [Num; 3],
+    vfov: Num,
+    #[serde(default = "default_aperture")]
+    aperture: Num,
+    #[serde(default = "default_focus_distance")]
+    focus_distance: Num,
+    #[serde(default = "default_exposure")]
+    exposure: [Num; 2],
+}
+
+fn default_vup() -> [Num; 3] {
+    [0., 1., 0.]
+}
+
+fn default_aperture() -> Num {
+    Camera::APERTURE
+}
+
+fn default_focus_distance() -> Num {
+    Camera::FOCUS_DISTANCE
+}
+
+fn default_exposure() -> [Num; 2] {
+    [0., 1.]
+}
+
+impl CameraDesc {
+    fn build(&self, aspect_ratio: Num) -> Camera {
+        Camera::new(
+            vector(self.look_from),
+            vector(self.look_at),
+            vector(self.vup),
+            self.vfov,
+            aspect_ratio,
+            Lens {
+                aperture: self.aperture,
+                focus_distance: self.focus_distance,
+            },
+            self.exposure[0]..self.exposure[1],
+        )
+    }
+}
+
+// `"sky"`, `"black"`, `{ solid = [r, g, b] }` or `{ gradient = { bottom = .., top = .. } }`
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum BackgroundDesc {
+    #[default]
+    Sky,
+    Black,
+    Solid([Num; 3]),
+    Gradient {
+        bottom: [Num; 3],
+        top: [Num; 3],
+    },
+}
+
+impl BackgroundDesc {
+    fn build(&self) -> Background {
+        match *self {
+            BackgroundDesc::Sky => Background::default(),
+            BackgroundDesc::Black => Background::Black,
+            BackgroundDesc::Solid(color) => Background::Solid(vector(color)),
+            BackgroundDesc::Gradient { bottom, top } => Background::Gradient {
+                bottom: vector(bottom),
+                top: vector(top),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDesc {
+    Lambertian {
+        albedo: TextureDesc,
+    },
+    Metal {
+        albedo: TextureDesc,
+        #[serde(default)]
+        fuzz: Num,
+    },
+    Dielectric {
+        ir: Num,
+    },
+    DiffuseLight {
+        emit: [Num; 3],
+    },
+    Isotropic {
+        albedo: TextureDesc,
+    },
+}
+
+// A plain `[r, g, b]` color, or a table naming the kind of texture
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TextureDesc {
+    Solid([Num; 3]),
+    Texture(TextureKind),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum TextureKind {
+    Checker {
+        odd: Box<TextureDesc>,
+        even: Box<TextureDesc>,
+        scale: Num,
+    },
+    Image(PathBuf),
+    Noise {
+        pattern: PatternDesc,
+        #[serde(default = "default_noise_scale")]
+        scale: Num,
+    },
+}
+
+fn default_noise_scale() -> Num {
+    1.
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum PatternDesc {
+    Smooth,
+    Turbulence,
+    Marble,
+    Wood,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDesc {
+    Sphere {
+        center: [Num; 3],
+        radius: Num,
+        material: String,
+    },
+    MovingSphere {
+        center0: [Num; 3],
+        center1: [Num; 3],
+        #[serde(default = "default_exposure")]
+        time: [Num; 2],
+        radius: Num,
+        material: String,
+    },
+    XyRect {
+        x: [Num; 2],
+        y: [Num; 2],
+        k: Num,
+        material: String,
+    },
+    XzRect {
+        x: [Num; 2],
+        z: [Num; 2],
+        k: Num,
+        material: String,
+    },
+    YzRect {
+        y: [Num; 2],
+        z: [Num; 2],
+        k: Num,
+        material: String,
+    },
+    Cuboid {
+        min: [Num; 3],
+        max: [Num; 3],
+        material: String,
+    },
+    // Wavefront .obj file, `material` is used for faces without a .mtl material
+    Mesh {
+        path: PathBuf,
+        material: String,
+    },
+    ConstantMedium {
+        density: Num,
+        albedo: TextureDesc,
+        boundary: Box<ObjectDesc>,
+    },
+    Translate {
+        offset: [Num; 3],
+        object: Box<ObjectDesc>,
+    },
+    Rotate {
+        #[serde(default = "default_vup")]
+        axis: [Num; 3],
+        degrees: Num,
+        object: Box<ObjectDesc>,
+    },
+    Scale {
+        factor: [Num; 3],
+        object: Box<ObjectDesc>,
+    },
+}
+
+// Turns the descriptions into scene types, `field` is the path to the description being built
+// for error messages, e.g. `objects[2].object.material`
+struct Builder<'a> {
+    path: &'a Path,
+    dir: &'a Path,
+    perlin: Arc<Perlin>,
+    exposure: Range<Num>,
+    materials: BTreeMap<String, Material>,
+}
+
+impl<'a> Builder<'a> {
+    fn invalid(&self, field: &str, message: String) -> SceneError {
+        SceneError::Invalid {
+            path: self.path.into(),
+            field: field.to_string(),
+            message,
+        }
+    }
+
+    // `[start, end]` as a range, rejecting empty and inverted ones that nothing could be in
+    fn time_range(&self, time: [Num; 2], field: &str) -> Result<Range<Num>, SceneError> {
+        if time[0] < time[1] {
+            Ok(time[0]..time[1])
+        } else {
+            Err(self.invalid(field, "the end must come after the start".into()))
+        }
+    }
+
+    fn image(&self, desc: &ImageDesc) -> Result<Image, SceneError> {
+        if desc.width < 2 {
+            return Err(self.invalid("image.width", "must be at least 2".into()));
+        }
+        let image = match (desc.height, desc.aspect_ratio) {
+            (Some(_), Some(_)) => {
+                return Err(self.invalid(
+                    "image",
+                    "give either `height` or `aspect_ratio`, not both".into(),
+                ))
+            }
+            (Some(height), None) => Image {
+                aspect_ratio: desc.width as Num / height as Num,
+                width: desc.width,
+                height,
+            },
+            (None, aspect_ratio) => {
+                Image::from_width(aspect_ratio.unwrap_or(Camera::ASPECT_RATIO), desc.width)
+            }
+        };
+        if image.height < 2 {
+            return Err(self.invalid("image.height", "must be at least 2".into()));
+        }
+        Ok(image)
+    }
+
+    fn material(&self, desc: &MaterialDesc, field: &str) -> Result<Material, SceneError> {
+        Ok(match desc {
+            MaterialDesc::Lambertian { albedo } => Material::Lambertian {
+                albedo: self.texture(albedo, &format!("{}.albedo", field))?,
+            },
+            MaterialDesc::Metal { albedo, fuzz } => Material::Metal {
+                albedo: self.texture(albedo, &format!("{}.albedo", field))?,
+                fuzz: *fuzz,
+            },
+            MaterialDesc::Dielectric { ir } => Material::Dielectric { ir: *ir },
+            MaterialDesc::DiffuseLight { emit } => Material::DiffuseLight {
+                emit: vector(*emit),
+            },
+            MaterialDesc::Isotropic { albedo } => Material::Isotropic {
+                albedo: self.texture(albedo, &format!("{}.albedo", field))?,
+            },
+        })
+    }
+
+    fn texture(&self, desc: &TextureDesc, field: &str) -> Result<Texture, SceneError> {
+        let kind = match desc {
+            TextureDesc::Solid(color) => return Ok(vector(*color).into()),
+            TextureDesc::Texture(kind) => kind,
+        };
+        Ok(match kind {
+            TextureKind::Checker { odd, even, scale } => {
+                if *scale == 0. {
+                    return Err(self.invalid(
+                        &format!("{}.checker.scale", field),
+                        "must not be zero".into(),
+                    ));
+                }
+                Texture::Checker {
+                    odd: Box::new(self.texture(odd, &format!("{}.checker.odd", field))?),
+                    even: Box::new(self.texture(even, &format!("{}.checker.even", field))?),
+                    scale: *scale,
+                }
+            }
+            TextureKind::Image(path) => {
+                let image = ImageTexture::load(self.dir.join(path)).map_err(|e| {
+                    self.invalid(
+                        &format!("{}.image", field),
+                        format!("cannot load {}: {}", path.display(), e),
+                    )
+                })?;
+                Texture::Image(Arc::new(image))
+            }
+            TextureKind::Noise { pattern, scale } => Texture::Noise {
+                perlin: self.perlin.clone(),
+                pattern: match pattern {
+                    PatternDesc::Smooth => NoisePattern::Smooth,
+                    PatternDesc::Turbulence => NoisePattern::Turbulence,
+                    PatternDesc::Marble => NoisePattern::Marble,
+                    PatternDesc::Wood => NoisePattern::Wood,
+                },
+                scale: *scale,
+            },
+        })
+    }
+
+    fn named_material(&self, name: &str, field: &str) -> Result<Material, SceneError> {
+        self.materials
+            .get(name)
+            .cloned()
+            .ok_or_else(|| self.invalid(field, format!("unknown material `{}`", name)))
+    }
+
+    // Copy of `desc` that rays can be aimed at, if it is an emitter of a shape that supports it
+    fn light(&self, desc: &ObjectDesc, field: &str) -> Result<Option<Box<dyn Light>>, SceneError> {
+        let emitter = |name: &str| {
+            let mat = self.named_material(name, &format!("{}.material", field))?;
+            Ok(matches!(mat, Material::DiffuseLight { .. }).then_some(mat))
+        };
+
+        Ok(match desc {
+            ObjectDesc::Sphere {
+                center,
+                radius,
+                material,
+            } => emitter(material)?
+                .map(|mat| Box::new(Sphere::new(vector(*center), *radius, mat)) as Box<dyn Light>),
+            ObjectDesc::XyRect { x, y, k, material } => emitter(material)?.map(|mat| {
+                Box::new(XyRect::new(x[0]..x[1], y[0]..y[1], *k, mat)) as Box<dyn Light>
+            }),
+            ObjectDesc::XzRect { x, z, k, material } => emitter(material)?.map(|mat| {
+                Box::new(XzRect::new(x[0]..x[1], z[0]..z[1], *k, mat)) as Box<dyn Light>
+            }),
+            ObjectDesc::YzRect { y, z, k, material } => emitter(material)?.map(|mat| {
+                Box::new(YzRect::new(y[0]..y[1], z[0]..z[1], *k, mat)) as Box<dyn Light>
+            }),
+            _ => None,
+        })
+    }
+
+    fn object(&self, desc: &ObjectDesc, field: &str) -> Result<Box<dyn Hittable>, SceneError> {
+        let material = |name: &str| self.named_material(name, &format!("{}.material", field));
+        let inner =
+            |object: &ObjectDesc, name: &str| self.object(object, &format!("{}.{}", field, name));
+
+        Ok(match desc {
+            ObjectDesc::Sphere {
+                center,
+                radius,
+                material: name,
+            } => Box::new(Sphere::new(vector(*center), *radius, material(name)?)),
+            ObjectDesc::MovingSphere {
+                center0,
+                center1,
+                time,
+                radius,
+                material: name,
+            } => Box::new(MovingSphere::new(
+                vector(*center0),
+                vector(*center1),
+                self.time_range(*time, &format!("{}.time", field))?,
+                *radius,
+                material(name)?,
+            )),
+            ObjectDesc::XyRect {
+                x,
+                y,
+                k,
+                material: name,
+            } => Box::new(XyRect::new(x[0]..x[1], y[0]..y[1], *k, material(name)?)),
+            ObjectDesc::XzRect {
+                x,
+                z,
+                k,
+                material: name,
+            } => Box::new(XzRect::new(x[0]..x[1], z[0]..z[1], *k, material(name)?)),
+            ObjectDesc::YzRect {
+                y,
+                z,
+                k,
+                material: name,
+            } => Box::new(YzRect::new(y[0]..y[1], z[0]..z[1], *k, material(name)?)),
+            ObjectDesc::Cuboid {
+                min,
+                max,
+                material: name,
+            } => Box::new(Cuboid::new(vector(*min), vector(*max), material(name)?)),
+            ObjectDesc::Mesh {
+                path,
+                material: name,
+            } => {
+                let mesh = obj::load(self.dir.join(path), material(name)?).map_err(|e| {
+                    self.invalid(
+                        &format!("{}.path", field),
+                        format!("cannot load {}: {}", path.display(), e),
+                    )
+                })?;
+                Box::new(Bvh::new(mesh, self.exposure.clone()))
+            }
+            ObjectDesc::ConstantMedium {
+                density,
+                albedo,
+                boundary,
+            } => {
+                if !(density.is_finite() && *density > 0.) {
+                    return Err(self.invalid(
+                        &format!("{}.density", field),
+                        "must be a positive number".into(),
+                    ));
+                }
+                Box::new(ConstantMedium::new(
+                    inner(boundary, "boundary")?,
+                    *density,
+                    self.texture(albedo, &format!("{}.albedo", field))?,
+                ))
+            }
+            ObjectDesc::Translate { offset, object } => {
+                Box::new(Translate::new(inner(object, "object")?, vector(*offset)))
+            }
+            ObjectDesc::Rotate {
+                axis,
+                degrees,
+                object,
+            } => {
+                if *axis == [0.; 3] {
+                    return Err(self.invalid(&format!("{}.axis", field), "must not be zero".into()));
+                }
+                Box::new(Rotate::new(
+                    inner(object, "object")?,
+                    vector(*axis),
+                    *degrees,
+                ))
+            }
+            ObjectDesc::Scale { factor, object } => {
+                if factor.contains(&0.) {
+                    return Err(
+                        self.invalid(&format!("{}.factor", field), "no factor can be zero".into())
+                    );
+                }
+                Box::new(Scale::new(inner(object, "object")?, vector(*factor)))
+            }
+        })
+    }
+}
+
+fn vector([x, y, z]: [Num; 3]) -> Vector3 {
+    Vector3::new(x, y, z)
+}
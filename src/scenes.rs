@@ -0,0 +1,442 @@
+use crate::background::Background;
+use crate::camera::{Camera, Lens};
+use crate::hittable::Hittable;
+use crate::image::Image;
+use crate::light::Lights;
+use crate::material::Material;
+use crate::medium::ConstantMedium;
+use crate::perlin::{NoisePattern, Perlin};
+use crate::scene::Scene;
+use crate::shapes::{Cuboid, MovingSphere, Sphere, XyRect, XzRect, YzRect};
+use crate::texture::Texture;
+use crate::transform::{Rotate, Translate};
+use crate::vec3::{Color, Point3, Vector3};
+use crate::world::World;
+use crate::Num;
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::sync::Arc;
+
+/// Scene hard-coded in Rust, selectable by name from the command line.
+pub struct BuiltinScene {
+    pub name: &'static str,
+    pub description: &'static str,
+    build: fn(&mut StdRng) -> Scene,
+}
+
+impl BuiltinScene {
+    /// Builds the scene, `rng` drives any random placement.
+    pub fn build(&self, rng: &mut StdRng) -> Scene {
+        (self.build)(rng)
+    }
+}
+
+pub const SCENES: &[BuiltinScene] = &[
+    BuiltinScene {
+        name: "final_scene",
+        description: "Random spheres around three big ones, from the cover of the first book",
+        build: |rng| Scene {
+            world: final_scene(rng),
+            ..sky_view(
+                Point3::new(13., 2., 3.),
+                Point3::zeros(),
+                Lens::default(),
+                500,
+            )
+        },
+    },
+    BuiltinScene {
+        name: "three_spheres",
+        description: "Glass, diffuse and metal spheres on a yellow ground",
+        build: |_| Scene {
+            world: three_spheres(),
+            ..sky_view(
+                Point3::new(-2., 2., 1.),
+                Point3::new(0., 0., -1.),
+                Lens {
+                    focus_distance: 3.4,
+                    ..Lens::default()
+                },
+                100,
+            )
+        },
+    },
+    BuiltinScene {
+        name: "perlin_spheres",
+        description: "Marble and wood Perlin noise textures",
+        build: |rng| Scene {
+            world: perlin_spheres(rng),
+            ..sky_view(
+                Point3::new(13., 2., 3.),
+                Point3::zeros(),
+                Lens::default(),
+                100,
+            )
+        },
+    },
+    BuiltinScene {
+        name: "simple_light",
+        description: "Spheres lit by a single spherical light",
+        build: |_| Scene {
+            world: simple_light(),
+            lights: Lights(vec![Box::new(simple_light_lamp())]),
+            background: Background::Black,
+            ..sky_view(
+                Point3::new(26., 3., 6.),
+                Point3::new(0., 2., 0.),
+                Lens::default(),
+                400,
+            )
+        },
+    },
+    BuiltinScene {
+        name: "cornell_box",
+        description: "Cornell box with two rotated blocks",
+        build: |_| Scene {
+            world: cornell_box(),
+            ..cornell_view()
+        },
+    },
+    BuiltinScene {
+        name: "cornell_smoke",
+        description: "Cornell box with blocks of smoke and fog",
+        build: |_| Scene {
+            world: cornell_smoke(),
+            ..cornell_view()
+        },
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static BuiltinScene> {
+    SCENES.iter().find(|scene| scene.name == name)
+}
+
+// Empty scene under the sky
+fn sky_view(look_from: Point3, look_at: Point3, lens: Lens, samples: usize) -> Scene {
+    Scene {
+        camera: Camera::new(
+            look_from,
+            look_at,
+            Vector3::new(0., 1., 0.),
+            20.,
+            Camera::ASPECT_RATIO,
+            lens,
+            0. ..1.,
+        ),
+        image: Image::from_width(Camera::ASPECT_RATIO, 1200),
+        world: World(vec![]),
+        lights: Lights(vec![]),
+        background: Background::default(),
+        samples,
+        max_depth: 50,
+    }
+}
+
+// Square image looking into the Cornell box through a pinhole
+fn cornell_view() -> Scene {
+    Scene {
+        camera: Camera::new(
+            Point3::new(278., 278., -800.),
+            Point3::new(278., 278., 0.),
+            Vector3::new(0., 1., 0.),
+            40.,
+            1.,
+            Lens {
+                aperture: 0.,
+                ..Lens::default()
+            },
+            0. ..1.,
+        ),
+        image: Image::from_width(1., 600),
+        world: World(vec![]),
+        lights: cornell_lights(),
+        background: Background::Black,
+        samples: 200,
+        max_depth: 50,
+    }
+}
+
+fn three_spheres() -> World {
+    World(vec![
+        // Ground
+        Box::new(Sphere::new(
+            Point3::new(0., -100.5, -1.),
+            100.,
+            Material::Lambertian {
+                albedo: Color::new(0.8, 0.8, 0.).into(),
+            },
+        )),
+        //Left
+        Box::new(Sphere::new(
+            Point3::new(-1., 0., -1.),
+            0.5,
+            Material::Dielectric { ir: 1.5 },
+        )),
+        // Inner left
+        Box::new(Sphere::new(
+            Point3::new(-1., 0., -1.),
+            -0.4,
+            Material::Dielectric { ir: 1.5 },
+        )),
+        // Center
+        Box::new(Sphere::new(
+            Point3::new(0., 0., -1.),
+            0.5,
+            Material::Lambertian {
+                albedo: Color::new(0.1, 0.2, 0.5).into(),
+            },
+        )),
+        //Right
+        Box::new(Sphere::new(
+            Point3::new(1., 0., -1.),
+            0.5,
+            Material::Metal {
+                albedo: Color::new(0.8, 0.6, 0.2).into(),
+                fuzz: 0.0,
+            },
+        )),
+    ])
+}
+
+fn final_scene<R: Rng>(rng: &mut R) -> World {
+    let mut world = World(vec![]);
+    let ground = Box::new(Sphere::new(
+        Point3::new(0., -1000., 0.),
+        1000.,
+        Material::Lambertian {
+            albedo: Texture::Checker {
+                odd: Box::new(Color::new(0.2, 0.3, 0.1).into()),
+                even: Box::new(Color::from_elem(0.9).into()),
+                scale: 0.3,
+            },
+        },
+    ));
+
+    world.add(ground);
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = rng.gen::<Num>();
+            let center = Point3::new(
+                a as Num + 0.9 * rng.gen::<Num>(),
+                0.2,
+                b as Num + 0.9 * rng.gen::<Num>(),
+            );
+
+            if (center - Point3::new(4., 0.2, 0.)).length() > 0.9 {
+                match choose_mat {
+                    c if c < 0.8 => {
+                        let albedo = Color::random(rng) * Color::random(rng);
+                        // Bouncing balls, blurred over the camera's exposure
+                        let center2 = center + Vector3::new(0., rng.gen_range(0. ..0.5), 0.);
+                        world.add(Box::new(MovingSphere::new(
+                            center,
+                            center2,
+                            0. ..1.,
+                            0.2,
+                            Material::Lambertian {
+                                albedo: albedo.into(),
+                            },
+                        )));
+                    }
+                    c if c < 0.95 => {
+                        let albedo = Color::random_double(0.5..1., rng);
+                        let fuzz = rng.gen_range(0. ..0.5);
+                        world.add(Box::new(Sphere::new(
+                            center,
+                            0.2,
+                            Material::Metal {
+                                albedo: albedo.into(),
+                                fuzz,
+                            },
+                        )));
+                    }
+                    _ => {
+                        world.add(Box::new(Sphere::new(
+                            center,
+                            0.2,
+                            Material::Dielectric { ir: 1.5 },
+                        )));
+                    }
+                }
+            }
+        }
+    }
+    world.add(Box::new(Sphere::new(
+        Point3::new(0., 1., 0.),
+        1.,
+        Material::Dielectric { ir: 1.5 },
+    )));
+    world.add(Box::new(Sphere::new(
+        Point3::new(-4., 1., 0.),
+        1.,
+        Material::Lambertian {
+            albedo: Color::new(0.4, 0.2, 0.1).into(),
+        },
+    )));
+    world.add(Box::new(Sphere::new(
+        Point3::new(4., 1., 0.),
+        1.,
+        Material::Metal {
+            albedo: Color::new(0.7, 0.6, 0.5).into(),
+            fuzz: 0.0,
+        },
+    )));
+
+    world
+}
+
+// Only lit by the emissive sphere
+fn simple_light() -> World {
+    let mut world = World(vec![]);
+    world.add(Box::new(Sphere::new(
+        Point3::new(0., -1000., 0.),
+        1000.,
+        Material::Lambertian {
+            albedo: Color::from_elem(0.5).into(),
+        },
+    )));
+    world.add(Box::new(Sphere::new(
+        Point3::new(0., 2., 0.),
+        2.,
+        Material::Lambertian {
+            albedo: Color::new(0.4, 0.2, 0.1).into(),
+        },
+    )));
+    world.add(Box::new(simple_light_lamp()));
+
+    world
+}
+
+fn simple_light_lamp() -> Sphere {
+    Sphere::new(
+        Point3::new(0., 7., 0.),
+        2.,
+        Material::DiffuseLight {
+            emit: Color::from_elem(4.),
+        },
+    )
+}
+
+// Marble ground and sphere, next to a wooden one
+fn perlin_spheres<R: Rng>(rng: &mut R) -> World {
+    let perlin = Arc::new(Perlin::new(rng));
+    let marble = |scale| Material::Lambertian {
+        albedo: Texture::Noise {
+            perlin: perlin.clone(),
+            pattern: NoisePattern::Marble,
+            scale,
+        },
+    };
+
+    World(vec![
+        Box::new(Sphere::new(Point3::new(0., -1000., 0.), 1000., marble(4.))),
+        Box::new(Sphere::new(Point3::new(0., 2., 0.), 2., marble(4.))),
+        Box::new(Sphere::new(
+            Point3::new(0., 2., 4.5),
+            2.,
+            Material::Lambertian {
+                albedo: Texture::Noise {
+                    perlin: perlin.clone(),
+                    pattern: NoisePattern::Wood,
+                    scale: 1.,
+                },
+            },
+        )),
+    ])
+}
+
+// Cornell box, 555 units wide, lit by the ceiling light
+fn cornell_box() -> World {
+    let mut world = cornell_room();
+    let white = Material::Lambertian {
+        albedo: Color::from_elem(0.73).into(),
+    };
+    for block in cornell_blocks(white) {
+        world.add(block);
+    }
+
+    world
+}
+
+// Cornell box with the two blocks made of smoke and fog
+fn cornell_smoke() -> World {
+    let mut world = cornell_room();
+    // Only the boundaries matter to the media, not their material
+    let [tall, short] = cornell_blocks(Material::Dielectric { ir: 1. });
+    world.add(Box::new(ConstantMedium::new(
+        tall,
+        0.01,
+        Color::zeros().into(),
+    )));
+    world.add(Box::new(ConstantMedium::new(
+        short,
+        0.01,
+        Color::from_elem(1.).into(),
+    )));
+
+    world
+}
+
+// Walls and ceiling light of the Cornell box
+fn cornell_room() -> World {
+    let red = Material::Lambertian {
+        albedo: Color::new(0.65, 0.05, 0.05).into(),
+    };
+    let white = Material::Lambertian {
+        albedo: Color::from_elem(0.73).into(),
+    };
+    let green = Material::Lambertian {
+        albedo: Color::new(0.12, 0.45, 0.15).into(),
+    };
+    let light = Material::DiffuseLight {
+        emit: Color::from_elem(15.),
+    };
+
+    World(vec![
+        Box::new(YzRect::new(0. ..555., 0. ..555., 555., green)),
+        Box::new(YzRect::new(0. ..555., 0. ..555., 0., red)),
+        Box::new(XzRect::new(213. ..343., 227. ..332., 554., light)),
+        Box::new(XzRect::new(0. ..555., 0. ..555., 0., white.clone())),
+        Box::new(XzRect::new(0. ..555., 0. ..555., 555., white.clone())),
+        Box::new(XyRect::new(0. ..555., 0. ..555., 555., white)),
+    ])
+}
+
+// Ceiling light of the Cornell box, the material doesn't matter
+fn cornell_lights() -> Lights {
+    Lights(vec![Box::new(XzRect::new(
+        213. ..343.,
+        227. ..332.,
+        554.,
+        Material::DiffuseLight {
+            emit: Color::zeros(),
+        },
+    ))])
+}
+
+// Tall and short block of the Cornell box
+fn cornell_blocks(mat: Material) -> [Box<dyn Hittable>; 2] {
+    [
+        Box::new(Translate::new(
+            Box::new(Rotate::new(
+                Box::new(Cuboid::new(
+                    Point3::zeros(),
+                    Point3::new(165., 330., 165.),
+                    mat.clone(),
+                )),
+                Vector3::new(0., 1., 0.),
+                15.,
+            )),
+            Vector3::new(265., 0., 295.),
+        )),
+        Box::new(Translate::new(
+            Box::new(Rotate::new(
+                Box::new(Cuboid::new(Point3::zeros(), Point3::from_elem(165.), mat)),
+                Vector3::new(0., 1., 0.),
+                -18.,
+            )),
+            Vector3::new(130., 0., 65.),
+        )),
+    ]
+}
@@ -1,11 +1,17 @@
+use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
+use crate::light::Light;
 use crate::material::Material;
+use crate::pdf::Onb;
 use crate::ray::Ray;
-use crate::vec3::Point3;
+use crate::vec3::{Point3, Vector3};
+use crate::world::World;
 use crate::Num;
+use rand::{Rng, RngCore};
+use std::f32::consts::PI;
 use std::ops::Range;
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Sphere {
     center: Point3,
     radius: Num,
@@ -13,7 +19,7 @@ pub struct Sphere {
 }
 
 impl Sphere {
-    pub(crate) fn new(center: Point3, radius: Num, mat: Material) -> Self {
+    pub fn new(center: Point3, radius: Num, mat: Material) -> Self {
         Self {
             center,
             radius,
@@ -23,30 +29,353 @@ impl Sphere {
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: Ray, range: Range<Num>) -> Option<HitRecord> {
-        let oc = ray.origin - self.center;
-        let a = ray.direction.length_squared();
-        let half_b = oc.dot(ray.direction);
-        let c = oc.length_squared() - self.radius * self.radius;
-
-        let discriminant = half_b * half_b - a * c;
-        if discriminant < 0. {
-            return None;
+    fn hit(&self, ray: Ray, range: Range<Num>, _rng: &mut dyn RngCore) -> Option<HitRecord<'_>> {
+        hit_sphere(self.center, self.radius, &self.mat, ray, range)
+    }
+
+    fn bounding_box(&self, _time: Range<Num>) -> Option<Aabb> {
+        // Hollow spheres use a negative radius
+        let r = Vector3::from_elem(self.radius.abs());
+        Some(Aabb::new(self.center - r, self.center + r))
+    }
+}
+
+impl Light for Sphere {
+    // Uniform over the cone of directions the sphere covers seen from `origin`, or over all
+    // directions from inside
+    fn pdf_value(&self, origin: Point3, direction: Vector3) -> Num {
+        let Some(one_minus_cos_max) = self.cone(origin) else {
+            return 1. / (4. * PI);
+        };
+        let ray = Ray::from(origin, direction, 0.);
+        if hit_sphere(
+            self.center,
+            self.radius,
+            &self.mat,
+            ray,
+            0.001..Num::INFINITY,
+        )
+        .is_none()
+        {
+            return 0.;
         }
+        1. / (2. * PI * one_minus_cos_max)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut dyn RngCore) -> Vector3 {
+        let Some(one_minus_cos_max) = self.cone(origin) else {
+            return Vector3::random_unit_vector(&mut &mut *rng);
+        };
+        let (r1, r2) = (rng.gen::<Num>(), rng.gen::<Num>());
+        let phi = 2. * PI * r1;
+        let one_minus_z = r2 * one_minus_cos_max;
+        let sin_theta = (one_minus_z * (2. - one_minus_z)).sqrt();
+        let local = Vector3::new(
+            phi.cos() * sin_theta,
+            phi.sin() * sin_theta,
+            1. - one_minus_z,
+        );
+        Onb::new(self.center - origin).local(local)
+    }
+}
+
+impl Sphere {
+    // `1 - cos(theta_max)` of the cone the sphere covers seen from `origin`, `None` from inside.
+    // Written to stay accurate for small or distant spheres, where the cosine is close to 1.
+    fn cone(&self, origin: Point3) -> Option<Num> {
+        let sin2_max = self.radius * self.radius / (self.center - origin).length_squared();
+        (sin2_max < 1.).then(|| sin2_max / (1. + (1. - sin2_max).sqrt()))
+    }
+}
+
+/// Sphere whose center moves linearly from `center0` to `center1` over `time`.
+#[derive(Clone)]
+pub struct MovingSphere {
+    center0: Point3,
+    center1: Point3,
+    time: (Num, Num),
+    radius: Num,
+    mat: Material,
+}
+
+impl MovingSphere {
+    pub fn new(
+        center0: Point3,
+        center1: Point3,
+        time: Range<Num>,
+        radius: Num,
+        mat: Material,
+    ) -> Self {
+        Self {
+            center0,
+            center1,
+            time: (time.start, time.end),
+            radius,
+            mat,
+        }
+    }
+
+    pub fn center(&self, time: Num) -> Point3 {
+        let (t0, t1) = self.time;
+        self.center0 + ((time - t0) / (t1 - t0)) * (self.center1 - self.center0)
+    }
+}
 
-        let sqrt_disc = discriminant.sqrt();
+impl Hittable for MovingSphere {
+    fn hit(&self, ray: Ray, range: Range<Num>, _rng: &mut dyn RngCore) -> Option<HitRecord<'_>> {
+        hit_sphere(self.center(ray.time), self.radius, &self.mat, ray, range)
+    }
+
+    fn bounding_box(&self, time: Range<Num>) -> Option<Aabb> {
+        let r = Vector3::from_elem(self.radius.abs());
+        let (start, end) = (self.center(time.start), self.center(time.end));
+        Some(Aabb::new(start - r, start + r).surrounding(Aabb::new(end - r, end + r)))
+    }
+}
+
+fn hit_sphere(
+    center: Point3,
+    radius: Num,
+    mat: &Material,
+    ray: Ray,
+    range: Range<Num>,
+) -> Option<HitRecord<'_>> {
+    let oc = ray.origin - center;
+    let a = ray.direction.length_squared();
+    let half_b = oc.dot(ray.direction);
+    let c = oc.length_squared() - radius * radius;
 
-        let mut root = (-half_b - sqrt_disc) / a;
+    let discriminant = half_b * half_b - a * c;
+    if discriminant < 0. {
+        return None;
+    }
+
+    let sqrt_disc = discriminant.sqrt();
+
+    let mut root = (-half_b - sqrt_disc) / a;
+    if !range.contains(&root) {
+        root = (-half_b + sqrt_disc) / a;
         if !range.contains(&root) {
-            root = (-half_b + sqrt_disc) / a;
-            if !range.contains(&root) {
-                return None;
-            }
+            return None;
         }
+    }
+
+    let p = ray.at(root);
+    let outward_normal = (p - center) / radius;
+
+    Some(HitRecord::new(
+        ray,
+        outward_normal,
+        p,
+        root,
+        // A negative radius turns the normal inwards, but not the texture
+        sphere_uv((p - center) / radius.abs()),
+        mat,
+    ))
+}
+
+// `(u, v)` of a point on the unit sphere: u is the angle around the Y axis starting from -X,
+// v is the angle from -Y to +Y
+fn sphere_uv(p: Point3) -> (Num, Num) {
+    let theta = (-p.y).acos();
+    let phi = (-p.z).atan2(p.x) + PI;
+
+    (phi / (2. * PI), theta / PI)
+}
+
+// Rectangle in the plane `axes.2 = k`, spanning `a` along `axes.0` and `b` along `axes.1`,
+// facing +`axes.2` or, if `flipped`, -`axes.2`
+#[derive(Clone)]
+struct AxisRect {
+    axes: (usize, usize, usize),
+    a: (Num, Num),
+    b: (Num, Num),
+    k: Num,
+    flipped: bool,
+    mat: Material,
+}
+
+impl AxisRect {
+    fn new(
+        axes: (usize, usize, usize),
+        a: Range<Num>,
+        b: Range<Num>,
+        k: Num,
+        mat: Material,
+    ) -> Self {
+        Self {
+            axes,
+            a: (a.start, a.end),
+            b: (b.start, b.end),
+            k,
+            flipped: false,
+            mat,
+        }
+    }
 
-        let p = ray.at(root);
-        let outward_normal = (p - self.center) / self.radius;
+    fn point(&self, a: Num, b: Num, k: Num) -> Point3 {
+        let mut p = [0.; 3];
+        p[self.axes.0] = a;
+        p[self.axes.1] = b;
+        p[self.axes.2] = k;
+        Point3::new(p[0], p[1], p[2])
+    }
+}
+
+impl AxisRect {
+    fn intersect(&self, ray: Ray, range: Range<Num>) -> Option<HitRecord<'_>> {
+        let (a_axis, b_axis, k_axis) = self.axes;
+        let t = (self.k - ray.origin[k_axis]) / ray.direction[k_axis];
+        if !range.contains(&t) {
+            return None;
+        }
+
+        let p = ray.at(t);
+        let (a, b) = (p[a_axis], p[b_axis]);
+        if a < self.a.0 || a > self.a.1 || b < self.b.0 || b > self.b.1 {
+            return None;
+        }
+
+        let uv = (
+            (a - self.a.0) / (self.a.1 - self.a.0),
+            (b - self.b.0) / (self.b.1 - self.b.0),
+        );
+        let outward_normal = self.point(0., 0., if self.flipped { -1. } else { 1. });
+
+        Some(HitRecord::new(ray, outward_normal, p, t, uv, &self.mat))
+    }
+}
+
+impl Hittable for AxisRect {
+    fn hit(&self, ray: Ray, range: Range<Num>, _rng: &mut dyn RngCore) -> Option<HitRecord<'_>> {
+        self.intersect(ray, range)
+    }
+
+    fn bounding_box(&self, _time: Range<Num>) -> Option<Aabb> {
+        // Pad the flat dimension so the box never has zero width
+        const PAD: Num = 0.0001;
+        Some(Aabb::new(
+            self.point(self.a.0, self.b.0, self.k - PAD),
+            self.point(self.a.1, self.b.1, self.k + PAD),
+        ))
+    }
+}
+
+impl Light for AxisRect {
+    fn pdf_value(&self, origin: Point3, direction: Vector3) -> Num {
+        let Some(rec) = self.intersect(Ray::from(origin, direction, 0.), 0.001..Num::INFINITY)
+        else {
+            return 0.;
+        };
+
+        // Converts the uniform density over the area to one over solid angle
+        let area = (self.a.1 - self.a.0) * (self.b.1 - self.b.0);
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = (direction.dot(rec.normal) / direction.length()).abs();
+        distance_squared / (cosine * area)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut dyn RngCore) -> Vector3 {
+        let a = rng.gen_range(self.a.0..self.a.1);
+        let b = rng.gen_range(self.b.0..self.b.1);
+        self.point(a, b, self.k) - origin
+    }
+}
+
+/// Rectangle in the plane `z = k`, facing +Z.
+#[derive(Clone)]
+pub struct XyRect(AxisRect);
+
+impl XyRect {
+    pub fn new(x: Range<Num>, y: Range<Num>, k: Num, mat: Material) -> Self {
+        Self(AxisRect::new((0, 1, 2), x, y, k, mat))
+    }
+}
+
+/// Rectangle in the plane `y = k`, facing +Y.
+#[derive(Clone)]
+pub struct XzRect(AxisRect);
+
+impl XzRect {
+    pub fn new(x: Range<Num>, z: Range<Num>, k: Num, mat: Material) -> Self {
+        Self(AxisRect::new((0, 2, 1), x, z, k, mat))
+    }
+}
+
+/// Rectangle in the plane `x = k`, facing +X.
+#[derive(Clone)]
+pub struct YzRect(AxisRect);
+
+impl YzRect {
+    pub fn new(y: Range<Num>, z: Range<Num>, k: Num, mat: Material) -> Self {
+        Self(AxisRect::new((1, 2, 0), y, z, k, mat))
+    }
+}
+
+macro_rules! impl_hittable_for_rect {
+    ($($rect:ty),*) => {
+        $(
+            impl $rect {
+                // The same rectangle facing the other way
+                fn flipped(mut self) -> Self {
+                    self.0.flipped = !self.0.flipped;
+                    self
+                }
+            }
+
+            impl Hittable for $rect {
+                fn hit(&self, ray: Ray, range: Range<Num>, rng: &mut dyn RngCore) -> Option<HitRecord<'_>> {
+                    self.0.hit(ray, range, rng)
+                }
+
+                fn bounding_box(&self, time: Range<Num>) -> Option<Aabb> {
+                    self.0.bounding_box(time)
+                }
+            }
+
+            impl Light for $rect {
+                fn pdf_value(&self, origin: Point3, direction: Vector3) -> Num {
+                    self.0.pdf_value(origin, direction)
+                }
+
+                fn random(&self, origin: Point3, rng: &mut dyn RngCore) -> Vector3 {
+                    self.0.random(origin, rng)
+                }
+            }
+        )*
+    };
+}
+
+impl_hittable_for_rect!(XyRect, XzRect, YzRect);
+
+/// Axis-aligned box between the corners `min` and `max`, made of six rectangles facing out.
+pub struct Cuboid {
+    min: Point3,
+    max: Point3,
+    sides: World,
+}
+
+impl Cuboid {
+    pub fn new(min: Point3, max: Point3, mat: Material) -> Self {
+        let sides = World(vec![
+            Box::new(XyRect::new(min.x..max.x, min.y..max.y, max.z, mat.clone())),
+            Box::new(XyRect::new(min.x..max.x, min.y..max.y, min.z, mat.clone()).flipped()),
+            Box::new(XzRect::new(min.x..max.x, min.z..max.z, max.y, mat.clone())),
+            Box::new(XzRect::new(min.x..max.x, min.z..max.z, min.y, mat.clone()).flipped()),
+            Box::new(YzRect::new(min.y..max.y, min.z..max.z, max.x, mat.clone())),
+            Box::new(YzRect::new(min.y..max.y, min.z..max.z, min.x, mat).flipped()),
+        ]);
+
+        Self { min, max, sides }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, ray: Ray, range: Range<Num>, rng: &mut dyn RngCore) -> Option<HitRecord<'_>> {
+        self.sides.hit(ray, range, rng)
+    }
 
-        Some(HitRecord::new(ray, outward_normal, p, root, self.mat))
+    fn bounding_box(&self, _time: Range<Num>) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max))
     }
 }
@@ -0,0 +1,207 @@
+use crate::perlin::{NoisePattern, Perlin};
+use crate::vec3::{Color, Point3};
+use crate::Num;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub enum Texture {
+    Solid(Color),
+    // 3D checker pattern alternating every `scale` units along each axis
+    Checker {
+        odd: Box<Texture>,
+        even: Box<Texture>,
+        scale: Num,
+    },
+    Image(Arc<ImageTexture>),
+    // Procedural Perlin noise, `scale` is the spatial frequency
+    Noise {
+        perlin: Arc<Perlin>,
+        pattern: NoisePattern,
+        scale: Num,
+    },
+}
+
+impl Texture {
+    pub fn value(&self, u: Num, v: Num, p: Point3) -> Color {
+        match self {
+            Texture::Solid(color) => *color,
+            Texture::Checker { odd, even, scale } => {
+                let cell = (p.x / scale).floor() + (p.y / scale).floor() + (p.z / scale).floor();
+                if cell.rem_euclid(2.) == 0. {
+                    even.value(u, v, p)
+                } else {
+                    odd.value(u, v, p)
+                }
+            }
+            Texture::Image(image) => image.value(u, v),
+            Texture::Noise {
+                perlin,
+                pattern,
+                scale,
+            } => {
+                let grey = perlin.pattern(*pattern, *scale * p);
+                match pattern {
+                    NoisePattern::Wood => {
+                        let (light, dark) =
+                            (Color::new(0.8, 0.6, 0.35), Color::new(0.4, 0.2, 0.07));
+                        (1. - grey) * light + grey * dark
+                    }
+                    _ => Color::from_elem(grey),
+                }
+            }
+        }
+    }
+}
+
+impl From<Color> for Texture {
+    fn from(color: Color) -> Self {
+        Texture::Solid(color)
+    }
+}
+
+/// Texture backed by an RGB image, addressed with `(u, v)` in `[0, 1]`.
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    // Row-major, top row first
+    data: Vec<Color>,
+}
+
+impl ImageTexture {
+    pub fn new(width: usize, height: usize, data: Vec<Color>) -> Self {
+        assert_eq!(data.len(), width * height);
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Reads a PNG file, or an ASCII (P3) or binary (P6) PPM file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Self::from_png(&bytes)
+        } else {
+            Self::from_ppm(&bytes)
+        }
+    }
+
+    // Any PNG, with the alpha channel dropped and gray spread over red, green and blue
+    fn from_png(bytes: &[u8]) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        buf.truncate(info.buffer_size());
+
+        let channels = info.color_type.samples();
+        let data = buf
+            .chunks_exact(channels)
+            .map(|p| match channels {
+                1 | 2 => Color::from_elem(p[0] as Num / 255.),
+                _ => Color::new(p[0] as Num, p[1] as Num, p[2] as Num) / 255.,
+            })
+            .collect();
+
+        Ok(Self::new(info.width as usize, info.height as usize, data))
+    }
+
+    fn from_ppm(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = PpmReader { bytes, pos: 0 };
+
+        let ascii = match reader.token() {
+            Some(b"P3") => true,
+            Some(b"P6") => false,
+            _ => return Err(invalid_data("not a PNG or a P3 or P6 PPM file")),
+        };
+        let width = reader.number()?;
+        let height = reader.number()?;
+        let max = reader.number()?;
+        if max == 0 {
+            return Err(invalid_data("PPM maximum value of 0"));
+        }
+        let max = max as Num;
+        let len = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(3))
+            .ok_or_else(|| invalid_data("unreasonable PPM size"))?;
+
+        let samples: Vec<Num> = if ascii {
+            (0..len)
+                .map(|_| reader.number().map(|n| n as Num / max))
+                .collect::<io::Result<_>>()?
+        } else {
+            // Exactly one whitespace byte separates the header from the raster
+            let raster = bytes.get(reader.pos + 1..).unwrap_or_default();
+            if max > 255. || raster.len() < len {
+                return Err(invalid_data("unsupported or truncated PPM raster"));
+            }
+            raster[..len].iter().map(|&b| b as Num / max).collect()
+        };
+        let data = samples
+            .chunks_exact(3)
+            .map(|c| Color::new(c[0], c[1], c[2]))
+            .collect();
+
+        Ok(Self::new(width, height, data))
+    }
+
+    pub fn value(&self, u: Num, v: Num) -> Color {
+        if self.data.is_empty() {
+            // Cyan, to make missing textures obvious
+            return Color::new(0., 1., 1.);
+        }
+
+        // Flip v so that v = 1 is the top row
+        let u = u.clamp(0., 1.);
+        let v = 1. - v.clamp(0., 1.);
+        let i = ((u * self.width as Num) as usize).min(self.width - 1);
+        let j = ((v * self.height as Num) as usize).min(self.height - 1);
+
+        self.data[j * self.width + i]
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+// Tokenizer for the whitespace separated PPM header, skipping `#` comments
+struct PpmReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PpmReader<'a> {
+    fn token(&mut self) -> Option<&'a [u8]> {
+        let bytes = self.bytes;
+        loop {
+            while self.pos < bytes.len() && bytes[self.pos].is_ascii_whitespace() {
+                self.pos += 1;
+            }
+            if self.pos < bytes.len() && bytes[self.pos] == b'#' {
+                while self.pos < bytes.len() && bytes[self.pos] != b'\n' {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+        let start = self.pos;
+        while self.pos < bytes.len() && !bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        (start < self.pos).then(|| &bytes[start..self.pos])
+    }
+
+    fn number(&mut self) -> io::Result<usize> {
+        self.token()
+            .and_then(|t| std::str::from_utf8(t).ok()?.parse().ok())
+            .ok_or_else(|| invalid_data("malformed PPM file"))
+    }
+}
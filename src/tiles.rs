@@ -0,0 +1,157 @@
+use crate::Num;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Rectangle of pixels rendered as one unit of work, `y` counted from the top row.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Order in which tiles are handed out to the render threads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileOrder {
+    // Left to right, top to bottom
+    Scanline,
+    // Outwards from the center of the image, which usually holds the subject
+    Spiral,
+    // Along a Hilbert curve, keeping consecutive tiles next to each other
+    Hilbert,
+}
+
+/// Snapshot of a render in progress, handed to the progress callback after every tile.
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    pub tiles_done: usize,
+    pub tiles_total: usize,
+    pub pixels_done: usize,
+    pub pixels_total: usize,
+    /// Samples taken so far, each a path traced from the camera
+    pub samples: u64,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Fraction of the pixels done, between 0 and 1.
+    pub fn fraction(&self) -> Num {
+        if self.pixels_total == 0 {
+            1.
+        } else {
+            self.pixels_done as Num / self.pixels_total as Num
+        }
+    }
+
+    pub fn samples_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0. {
+            self.samples as f64 / seconds
+        } else {
+            0.
+        }
+    }
+
+    /// Remaining time, extrapolated from the pace so far.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.pixels_done == 0 {
+            return None;
+        }
+        let remaining = (self.pixels_total - self.pixels_done) as f64 / self.pixels_done as f64;
+        Some(self.elapsed.mul_f64(remaining))
+    }
+}
+
+/// Splits a `width` by `height` image into tiles of at most `size` by `size` pixels, in `order`.
+pub fn tiles(width: usize, height: usize, size: usize, order: TileOrder) -> Vec<Tile> {
+    let size = size.max(1);
+    let columns = width.div_ceil(size);
+    let rows = height.div_ceil(size);
+
+    let mut grid: Vec<(usize, usize)> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .collect();
+    match order {
+        TileOrder::Scanline => {}
+        TileOrder::Spiral => {
+            // Ring by ring around the center tile, each ring clockwise from the top left
+            let cx = (columns as Num - 1.) / 2.;
+            let cy = (rows as Num - 1.) / 2.;
+            let key = |&(column, row): &(usize, usize)| {
+                let dx = column as Num - cx;
+                let dy = row as Num - cy;
+                let ring = dx.abs().max(dy.abs());
+                let angle = dy.atan2(dx) + std::f32::consts::PI * 0.75;
+                (ring, angle.rem_euclid(2. * std::f32::consts::PI))
+            };
+            grid.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+        }
+        TileOrder::Hilbert => {
+            let side = columns.max(rows).next_power_of_two();
+            grid.sort_by_key(|&(column, row)| hilbert_index(side, column, row));
+        }
+    }
+
+    grid.into_iter()
+        .map(|(column, row)| {
+            let x = column * size;
+            let y = row * size;
+            Tile {
+                x,
+                y,
+                width: size.min(width - x),
+                height: size.min(height - y),
+            }
+        })
+        .collect()
+}
+
+// Distance of `(x, y)` along the Hilbert curve filling a `side` by `side` square, `side` being a
+// power of two
+fn hilbert_index(side: usize, mut x: usize, mut y: usize) -> usize {
+    let mut d = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = ((x & s) > 0) as usize;
+        let ry = ((y & s) > 0) as usize;
+        d += s * s * ((3 * rx) ^ ry);
+        // Rotate the quadrant so the curve inside it starts and ends at the right corners
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    d
+}
+
+impl fmt::Display for TileOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TileOrder::Scanline => "scanline",
+            TileOrder::Spiral => "spiral",
+            TileOrder::Hilbert => "hilbert",
+        })
+    }
+}
+
+impl FromStr for TileOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "scanline" => Ok(TileOrder::Scanline),
+            "spiral" => Ok(TileOrder::Spiral),
+            "hilbert" => Ok(TileOrder::Hilbert),
+            _ => Err(format!(
+                "unknown tile order `{}`, expected scanline, spiral or hilbert",
+                s
+            )),
+        }
+    }
+}
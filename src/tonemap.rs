@@ -0,0 +1,187 @@
+use crate::vec3::Color;
+use crate::Num;
+use std::fmt;
+use std::str::FromStr;
+
+/// Curve compressing scene radiance into the displayable `[0, 1]` range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMap {
+    // Anything above 1 is clipped
+    Clamp,
+    // `L / (1 + L)` on the luminance, never quite reaching white
+    Reinhard,
+    // Reinhard reaching white at luminance `white`
+    ExtendedReinhard { white: Num },
+    // John Hable's Uncharted 2 curve
+    Filmic,
+    // Krzysztof Narkowicz's fit of the ACES reference rendering transform
+    Aces,
+}
+
+/// Opto-electronic transfer function, encoding linear light for the display.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Oetf {
+    // `sqrt`, as in the books
+    Gamma2,
+    Srgb,
+}
+
+/// How accumulated radiance becomes a displayable color: an exposure adjustment in stops, a tone
+/// curve and the transfer function.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisplayTransform {
+    pub exposure: Num,
+    pub tone_map: ToneMap,
+    pub oetf: Oetf,
+}
+
+impl Default for DisplayTransform {
+    fn default() -> Self {
+        Self {
+            exposure: 0.,
+            tone_map: ToneMap::Clamp,
+            oetf: Oetf::Gamma2,
+        }
+    }
+}
+
+impl DisplayTransform {
+    /// Display-encoded color in `[0, 1]` for the average radiance `radiance`.
+    pub fn apply(&self, radiance: Color) -> Color {
+        let exposed = Num::powf(2., self.exposure) * radiance;
+        let mapped = self.tone_map.apply(exposed);
+        let encode = |v: Num| self.oetf.encode(v.clamp(0., 1.));
+        Color::new(encode(mapped.x), encode(mapped.y), encode(mapped.z))
+    }
+
+    /// 8 bit color for the average radiance `radiance`.
+    pub fn rgb8(&self, radiance: Color) -> [u8; 3] {
+        let c = self.apply(radiance);
+        // The books' clamp and scale for their tone curve and gamma, so that images rendered with
+        // those come out the same as they always have
+        let (scale, max) = match (self.tone_map, self.oetf) {
+            (ToneMap::Clamp, Oetf::Gamma2) => (255.99, 0.99),
+            _ => (256., 0.999),
+        };
+        let quantize = |v: Num| (scale * v.clamp(0., max)) as u8;
+        [quantize(c.x), quantize(c.y), quantize(c.z)]
+    }
+}
+
+impl ToneMap {
+    pub fn apply(&self, c: Color) -> Color {
+        match *self {
+            ToneMap::Clamp => c,
+            ToneMap::Reinhard => {
+                let l = luminance(c);
+                c / (1. + l)
+            }
+            ToneMap::ExtendedReinhard { white } => {
+                let l = luminance(c);
+                c * (1. + l / (white * white)) / (1. + l)
+            }
+            ToneMap::Filmic => {
+                const EXPOSURE_BIAS: Num = 2.;
+                const WHITE: Num = 11.2;
+                let white_scale = 1. / hable(WHITE);
+                let f = |v: Num| hable(EXPOSURE_BIAS * v) * white_scale;
+                Color::new(f(c.x), f(c.y), f(c.z))
+            }
+            ToneMap::Aces => {
+                let f = |v: Num| (v * (2.51 * v + 0.03)) / (v * (2.43 * v + 0.59) + 0.14);
+                Color::new(f(c.x), f(c.y), f(c.z))
+            }
+        }
+    }
+}
+
+impl Oetf {
+    pub fn encode(&self, v: Num) -> Num {
+        match self {
+            Oetf::Gamma2 => v.sqrt(),
+            Oetf::Srgb => {
+                if v <= 0.003_130_8 {
+                    12.92 * v
+                } else {
+                    1.055 * v.powf(1. / 2.4) - 0.055
+                }
+            }
+        }
+    }
+}
+
+/// Rec. 709 relative luminance.
+pub fn luminance(c: Color) -> Num {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+fn hable(x: Num) -> Num {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
+
+impl fmt::Display for ToneMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToneMap::Clamp => f.write_str("clamp"),
+            ToneMap::Reinhard => f.write_str("reinhard"),
+            ToneMap::ExtendedReinhard { white } => write!(f, "extended-reinhard:{}", white),
+            ToneMap::Filmic => f.write_str("filmic"),
+            ToneMap::Aces => f.write_str("aces"),
+        }
+    }
+}
+
+// `clamp`, `reinhard`, `extended-reinhard[:WHITE]`, `filmic` or `aces`
+impl FromStr for ToneMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, white) = match s.split_once(':') {
+            Some((name, white)) => (name, Some(white)),
+            None => (s, None),
+        };
+        match (name, white) {
+            ("clamp", None) => Ok(ToneMap::Clamp),
+            ("reinhard", None) => Ok(ToneMap::Reinhard),
+            ("extended-reinhard", None) => Ok(ToneMap::ExtendedReinhard { white: 4. }),
+            ("extended-reinhard", Some(white)) => white
+                .parse()
+                .ok()
+                .filter(|&white: &Num| white.is_finite() && white > 0.)
+                .map(|white| ToneMap::ExtendedReinhard { white })
+                .ok_or_else(|| format!("invalid white point `{}`", white)),
+            ("filmic", None) => Ok(ToneMap::Filmic),
+            ("aces", None) => Ok(ToneMap::Aces),
+            _ => Err(format!(
+                "unknown tone map `{}`, expected clamp, reinhard, extended-reinhard[:WHITE], \
+                 filmic or aces",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Oetf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Oetf::Gamma2 => "gamma2",
+            Oetf::Srgb => "srgb",
+        })
+    }
+}
+
+impl FromStr for Oetf {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gamma2" => Ok(Oetf::Gamma2),
+            "srgb" => Ok(Oetf::Srgb),
+            _ => Err(format!(
+                "unknown transfer function `{}`, expected gamma2 or srgb",
+                s
+            )),
+        }
+    }
+}
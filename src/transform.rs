@@ -0,0 +1,158 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vector3};
+use crate::Num;
+use rand::RngCore;
+use std::ops::Range;
+
+/// Moves `object` by `offset`.
+pub struct Translate {
+    object: Box<dyn Hittable>,
+    offset: Vector3,
+}
+
+impl Translate {
+    pub fn new(object: Box<dyn Hittable>, offset: Vector3) -> Self {
+        Self { object, offset }
+    }
+}
+
+impl Hittable for Translate {
+    fn hit(&self, ray: Ray, range: Range<Num>, rng: &mut dyn RngCore) -> Option<HitRecord<'_>> {
+        let moved = Ray::from(ray.origin - self.offset, ray.direction, ray.time);
+        let mut rec = self.object.hit(moved, range, rng)?;
+        rec.p += self.offset;
+        Some(rec)
+    }
+
+    fn bounding_box(&self, time: Range<Num>) -> Option<Aabb> {
+        let bbox = self.object.bounding_box(time)?;
+        Some(Aabb::new(bbox.min + self.offset, bbox.max + self.offset))
+    }
+}
+
+/// Rotates `object` about `axis` through the origin, counterclockwise looking down the axis.
+pub struct Rotate {
+    object: Box<dyn Hittable>,
+    rotation: Matrix3,
+    // Rotations are orthogonal, so the inverse is the transpose
+    inverse: Matrix3,
+}
+
+impl Rotate {
+    pub fn new(object: Box<dyn Hittable>, axis: Vector3, degrees: Num) -> Self {
+        let rotation = Matrix3::rotation(axis.normalize(), degrees.to_radians());
+        Self {
+            object,
+            rotation,
+            inverse: rotation.transpose(),
+        }
+    }
+}
+
+impl Hittable for Rotate {
+    fn hit(&self, ray: Ray, range: Range<Num>, rng: &mut dyn RngCore) -> Option<HitRecord<'_>> {
+        let rotated = Ray::from(
+            self.inverse * ray.origin,
+            self.inverse * ray.direction,
+            ray.time,
+        );
+        let mut rec = self.object.hit(rotated, range, rng)?;
+        rec.p = self.rotation * rec.p;
+        rec.normal = self.rotation * rec.normal;
+        Some(rec)
+    }
+
+    fn bounding_box(&self, time: Range<Num>) -> Option<Aabb> {
+        let bbox = self.object.bounding_box(time)?;
+        Some(transform_box(bbox, |p| self.rotation * p))
+    }
+}
+
+/// Stretches `object` by `factor` along each axis.
+pub struct Scale {
+    object: Box<dyn Hittable>,
+    factor: Vector3,
+}
+
+impl Scale {
+    pub fn new(object: Box<dyn Hittable>, factor: Vector3) -> Self {
+        Self { object, factor }
+    }
+
+    fn inverse(&self) -> Vector3 {
+        Vector3::new(1. / self.factor.x, 1. / self.factor.y, 1. / self.factor.z)
+    }
+}
+
+impl Hittable for Scale {
+    fn hit(&self, ray: Ray, range: Range<Num>, rng: &mut dyn RngCore) -> Option<HitRecord<'_>> {
+        // The object space direction is left unnormalized so that `t` is the same in both spaces
+        let inverse = self.inverse();
+        let scaled = Ray::from(ray.origin * inverse, ray.direction * inverse, ray.time);
+        let mut rec = self.object.hit(scaled, range, rng)?;
+        rec.p *= self.factor;
+        // Normals transform by the inverse transpose, which for a scale is the inverse
+        rec.normal = (rec.normal * inverse).normalize();
+        Some(rec)
+    }
+
+    fn bounding_box(&self, time: Range<Num>) -> Option<Aabb> {
+        let bbox = self.object.bounding_box(time)?;
+        Some(transform_box(bbox, |p| p * self.factor))
+    }
+}
+
+// Box around the eight transformed corners of `bbox`
+fn transform_box<F: Fn(Point3) -> Point3>(bbox: Aabb, transform: F) -> Aabb {
+    (0..8)
+        .map(|i| {
+            let corner = Point3::new(
+                if i & 1 == 0 { bbox.min.x } else { bbox.max.x },
+                if i & 2 == 0 { bbox.min.y } else { bbox.max.y },
+                if i & 4 == 0 { bbox.min.z } else { bbox.max.z },
+            );
+            let p = transform(corner);
+            Aabb::new(p, p)
+        })
+        .reduce(Aabb::surrounding)
+        .unwrap()
+}
+
+// Row-major 3x3 matrix
+#[derive(Clone, Copy)]
+struct Matrix3([Vector3; 3]);
+
+impl Matrix3 {
+    // Rodrigues' rotation formula, `axis` must be a unit vector
+    fn rotation(axis: Vector3, radians: Num) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        let Vector3 { x, y, z } = axis;
+        let c = 1. - cos;
+        Self([
+            Vector3::new(cos + x * x * c, x * y * c - z * sin, x * z * c + y * sin),
+            Vector3::new(y * x * c + z * sin, cos + y * y * c, y * z * c - x * sin),
+            Vector3::new(z * x * c - y * sin, z * y * c + x * sin, cos + z * z * c),
+        ])
+    }
+
+    fn transpose(self) -> Self {
+        let [a, b, c] = self.0;
+        Self([
+            Vector3::new(a.x, b.x, c.x),
+            Vector3::new(a.y, b.y, c.y),
+            Vector3::new(a.z, b.z, c.z),
+        ])
+    }
+}
+
+// `mat3 * vec3`
+impl std::ops::Mul<Vector3> for Matrix3 {
+    type Output = Vector3;
+
+    fn mul(self, rhs: Vector3) -> Self::Output {
+        let [a, b, c] = self.0;
+        Vector3::new(a.dot(rhs), b.dot(rhs), c.dot(rhs))
+    }
+}
@@ -0,0 +1,135 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vector3};
+use crate::Num;
+use rand::RngCore;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Triangle with optional per-vertex normals and texture coordinates.
+///
+/// The material is shared so that the triangles of a mesh don't each carry a copy.
+#[derive(Clone)]
+pub struct Triangle {
+    vertices: [Point3; 3],
+    normals: Option<[Vector3; 3]>,
+    uvs: Option<[(Num, Num); 3]>,
+    mat: Arc<Material>,
+}
+
+impl Triangle {
+    pub fn new(
+        vertices: [Point3; 3],
+        normals: Option<[Vector3; 3]>,
+        uvs: Option<[(Num, Num); 3]>,
+        mat: Arc<Material>,
+    ) -> Self {
+        Self {
+            vertices,
+            normals,
+            uvs,
+            mat,
+        }
+    }
+
+    // Watertight ray/triangle intersection, see Woop, Benthin and Wald, "Watertight Ray/Triangle
+    // Intersection" (2013). Returns `t` and the barycentric weights of the three vertices.
+    fn intersect(&self, ray: Ray) -> Option<(Num, [Num; 3])> {
+        let dir = ray.direction;
+
+        // Permute the axes so that z is the dominant direction, keeping the winding order
+        let kz = if dir.x.abs() > dir.y.abs() && dir.x.abs() > dir.z.abs() {
+            0
+        } else if dir.y.abs() > dir.z.abs() {
+            1
+        } else {
+            2
+        };
+        let (mut kx, mut ky) = ((kz + 1) % 3, (kz + 2) % 3);
+        if dir[kz] < 0. {
+            std::mem::swap(&mut kx, &mut ky);
+        }
+
+        // Shear so the ray points down +z from the origin
+        let sx = dir[kx] / dir[kz];
+        let sy = dir[ky] / dir[kz];
+        let sz = 1. / dir[kz];
+
+        let [a, b, c] = self.vertices.map(|v| v - ray.origin);
+        let (ax, ay) = (a[kx] - sx * a[kz], a[ky] - sy * a[kz]);
+        let (bx, by) = (b[kx] - sx * b[kz], b[ky] - sy * b[kz]);
+        let (cx, cy) = (c[kx] - sx * c[kz], c[ky] - sy * c[kz]);
+
+        let mut u = cx * by - cy * bx;
+        let mut v = ax * cy - ay * cx;
+        let mut w = bx * ay - by * ax;
+
+        // Edges exactly through the ray are ambiguous in single precision, redo them in double
+        if u == 0. || v == 0. || w == 0. {
+            let (ax, ay, bx, by, cx, cy) = (
+                ax as f64, ay as f64, bx as f64, by as f64, cx as f64, cy as f64,
+            );
+            u = (cx * by - cy * bx) as Num;
+            v = (ax * cy - ay * cx) as Num;
+            w = (bx * ay - by * ax) as Num;
+        }
+
+        if (u < 0. || v < 0. || w < 0.) && (u > 0. || v > 0. || w > 0.) {
+            return None;
+        }
+
+        let det = u + v + w;
+        if det == 0. {
+            return None;
+        }
+
+        let t = (u * sz * a[kz] + v * sz * b[kz] + w * sz * c[kz]) / det;
+        Some((t, [u / det, v / det, w / det]))
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: Ray, range: Range<Num>, _rng: &mut dyn RngCore) -> Option<HitRecord<'_>> {
+        let (t, [b0, b1, b2]) = self.intersect(ray)?;
+        if !range.contains(&t) {
+            return None;
+        }
+
+        let [v0, v1, v2] = self.vertices;
+        let outward_normal = match self.normals {
+            Some([n0, n1, n2]) => (b0 * n0 + b1 * n1 + b2 * n2).normalize(),
+            None => (v1 - v0).cross(v2 - v0).normalize(),
+        };
+        let uv = match self.uvs {
+            Some([uv0, uv1, uv2]) => (
+                b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+                b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+            ),
+            None => (b1, b2),
+        };
+
+        Some(HitRecord::new(
+            ray,
+            outward_normal,
+            ray.at(t),
+            t,
+            uv,
+            &self.mat,
+        ))
+    }
+
+    fn bounding_box(&self, _time: Range<Num>) -> Option<Aabb> {
+        // Pad so that axis-aligned triangles don't get a flat box
+        const PAD: Num = 0.0001;
+        let [v0, v1, v2] = self.vertices;
+        let bbox = Aabb::new(v0, v0)
+            .surrounding(Aabb::new(v1, v1))
+            .surrounding(Aabb::new(v2, v2));
+        Some(Aabb::new(
+            bbox.min - Vector3::from_elem(PAD),
+            bbox.max + Vector3::from_elem(PAD),
+        ))
+    }
+}
@@ -1,8 +1,7 @@
 // lightweight class for vector3 class
 
 use super::Num;
-use rand::distributions::Distribution;
-use rand::{random, Rng};
+use rand::Rng;
 use std::ops;
 use std::ops::Range;
 
@@ -46,6 +45,11 @@ impl Vector3 {
         self / self.length()
     }
 
+    /// Largest of the three components.
+    pub fn max_element(&self) -> Num {
+        self.x.max(self.y).max(self.z)
+    }
+
     pub fn zeros() -> Self {
         Self::from_elem(0. as Num)
     }
@@ -64,12 +68,10 @@ impl Vector3 {
         }
     }
 
+    // Always takes three random numbers, unlike rejection sampling, so that samplers can keep
+    // their dimensions lined up from one sample to the next
     pub fn random_in_unit_sphere<R: Rng>(rng: &mut R) -> Self {
-        let mut v = Self::random_double(-1. ..1., rng);
-        while v.length_squared() >= 1. {
-            v = Self::random_double(-1. ..1., rng);
-        }
-        v
+        Self::random_unit_vector(rng) * rng.gen::<Num>().cbrt()
     }
 
     pub fn random_in_hemisphere<R: Rng>(normal: Self, rng: &mut R) -> Self {
@@ -81,8 +83,12 @@ impl Vector3 {
         }
     }
 
+    // Uniform in height and in angle around the axis, which is uniform over the sphere
     pub fn random_unit_vector<R: Rng>(rng: &mut R) -> Self {
-        Self::random_in_unit_sphere(rng).normalize()
+        let z = 1. - 2. * rng.gen::<Num>();
+        let phi = 2. * std::f32::consts::PI * rng.gen::<Num>();
+        let r = (1. - z * z).max(0.).sqrt();
+        Self::new(r * phi.cos(), r * phi.sin(), z)
     }
 
     pub fn near_zero(&self) -> bool {
@@ -99,7 +105,7 @@ impl Vector3 {
         let cos_theta = (-self).dot(other).min(1.0);
         let r_out_perp = etai_over_etat * (self + cos_theta * other);
         let r_out_parallel = -((1.0 - r_out_perp.length_squared()).abs()).sqrt() * other;
-        return r_out_perp + r_out_parallel;
+        r_out_perp + r_out_parallel
     }
 }
 
@@ -133,6 +139,20 @@ impl ops::DivAssign<Num> for Vector3 {
     }
 }
 
+// `vec3[axis]`
+impl ops::Index<usize> for Vector3 {
+    type Output = Num;
+
+    fn index(&self, axis: usize) -> &Self::Output {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vector3 axis out of range: {}", axis),
+        }
+    }
+}
+
 // `vec3 + vec3`
 impl ops::Add for Vector3 {
     type Output = Self;
@@ -222,5 +242,5 @@ impl Default for Vector3 {
     }
 }
 
-pub(crate) type Color = Vector3;
-pub(crate) type Point3 = Vector3;
+pub type Color = Vector3;
+pub type Point3 = Vector3;
@@ -1,8 +1,10 @@
 use std::ops::Range;
 
+use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::ray::Ray;
 use crate::Num;
+use rand::RngCore;
 
 pub struct World(pub Vec<Box<dyn Hittable>>);
 
@@ -13,16 +15,24 @@ impl World {
 }
 
 impl Hittable for World {
-    fn hit(&self, ray: Ray, range: Range<Num>) -> Option<HitRecord> {
+    fn hit(&self, ray: Ray, range: Range<Num>, rng: &mut dyn RngCore) -> Option<HitRecord<'_>> {
         let mut hit_record: Option<HitRecord> = None;
         let mut closest = range.end;
 
         for h in &self.0 {
-            if let Some(rec) = h.hit(ray, range.start..closest) {
+            if let Some(rec) = h.hit(ray, range.start..closest, rng) {
                 closest = rec.t;
                 hit_record = Some(rec);
             }
         }
         hit_record
     }
+
+    fn bounding_box(&self, time: Range<Num>) -> Option<Aabb> {
+        self.0
+            .iter()
+            .map(|h| h.bounding_box(time.clone()))
+            .reduce(|a, b| Some(a?.surrounding(b?)))
+            .flatten()
+    }
 }